@@ -0,0 +1,279 @@
+use std::borrow::Cow;
+
+use ratatui::style::{Color, Modifier, Style};
+
+use crate::log_buffer::raw_byte;
+
+/// Applies the escape sequences in a line of terminal output and returns the resulting chars with
+/// their styles.
+///
+/// SGR sequences set the style of the following chars. Carriage returns, cursor movement within the
+/// line, erase and backspace overwrite chars like a terminal would, so a progress bar that is
+/// redrawn in place shows its latest state; everything else (cursor up/down, OSC titles and
+/// hyperlinks, charset selection, ...) is dropped.
+pub fn parse(s: &str) -> Vec<(char, Style)> {
+    let mut line = LineBuffer::default();
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\x1b' => match chars.next() {
+                Some('[') => {
+                    let mut params = String::new();
+                    let mut command = None;
+                    for c in chars.by_ref() {
+                        if ('\x40'..='\x7e').contains(&c) {
+                            command = Some(c);
+                            break;
+                        }
+                        params.push(c);
+                    }
+                    if let Some(command) = command {
+                        line.csi(&params, command);
+                    }
+                }
+                Some(']') => {
+                    // OSC, terminated by BEL or ST (ESC \)
+                    while let Some(c) = chars.next() {
+                        if c == '\x07' || (c == '\x1b' && chars.next_if_eq(&'\\').is_some()) {
+                            break;
+                        }
+                    }
+                }
+                Some('(' | ')' | '*' | '+') => {
+                    chars.next();
+                }
+                _ => {}
+            },
+            '\r' => line.cursor = 0,
+            '\x08' => line.cursor = line.cursor.saturating_sub(1),
+            '\t' => {
+                let spaces = 8 - line.cursor % 8;
+                (0..spaces).for_each(|_| line.put(' '));
+            }
+            c if c.is_control() => {}
+            c if raw_byte(c).is_some() => line.put(char::REPLACEMENT_CHARACTER),
+            c => line.put(c),
+        }
+    }
+    line.cells
+}
+
+/// The text of a line of terminal output without escape sequences, see [`parse`].
+pub fn strip(s: &str) -> Cow<'_, str> {
+    if s.contains(['\x1b', '\r', '\x08', '\t']) {
+        Cow::Owned(parse(s).into_iter().map(|(c, _)| c).collect())
+    } else {
+        Cow::Borrowed(s)
+    }
+}
+
+/// How far a cursor movement can go, so that a bogus sequence like `ESC [99999999999C` in a log
+/// doesn't make a line of that many blanks.
+const MAX_COLUMN: usize = 4096;
+
+#[derive(Default)]
+struct LineBuffer {
+    cells: Vec<(char, Style)>,
+    cursor: usize,
+    style: Style,
+}
+
+impl LineBuffer {
+    fn put(&mut self, c: char) {
+        self.pad_to(self.cursor);
+        if self.cursor < self.cells.len() {
+            self.cells[self.cursor] = (c, self.style);
+        } else {
+            self.cells.push((c, self.style));
+        }
+        self.cursor += 1;
+    }
+
+    fn pad_to(&mut self, len: usize) {
+        if self.cells.len() < len {
+            self.cells.resize(len, (' ', Style::default()));
+        }
+    }
+
+    fn blank(&mut self, from: usize, to: usize) {
+        let to = to.min(self.cells.len());
+        for cell in self.cells.iter_mut().take(to).skip(from) {
+            *cell = (' ', Style::default());
+        }
+    }
+
+    fn csi(&mut self, params: &str, command: char) {
+        // private sequences such as `ESC [?25l` (hide cursor) don't affect the text
+        if params.starts_with(['?', '>', '=', '<']) {
+            return;
+        }
+        // a missing or zero parameter is the default
+        let n = |default: usize| match params.parse::<usize>() {
+            Ok(0) => default,
+            Ok(n) => n.min(MAX_COLUMN),
+            Err(_) if !params.is_empty() && params.bytes().all(|b| b.is_ascii_digit()) => {
+                MAX_COLUMN
+            }
+            Err(_) => default,
+        };
+        match command {
+            'm' => self.style = sgr(self.style, params),
+            'C' => {
+                self.cursor = self
+                    .cursor
+                    .saturating_add(n(1))
+                    .min(MAX_COLUMN.max(self.cursor))
+            }
+            'D' => self.cursor = self.cursor.saturating_sub(n(1)),
+            'G' => self.cursor = n(1).saturating_sub(1),
+            'K' => match params {
+                "1" => self.blank(0, self.cursor.saturating_add(1)),
+                "2" => self.blank(0, self.cells.len()),
+                _ => self.cells.truncate(self.cursor),
+            },
+            'X' => self.blank(self.cursor, self.cursor.saturating_add(n(1))),
+            'P' => {
+                let end = self.cursor.saturating_add(n(1)).min(self.cells.len());
+                if self.cursor < end {
+                    self.cells.drain(self.cursor..end);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Applies the SGR (select graphic rendition) parameters to `style`.
+fn sgr(mut style: Style, params: &str) -> Style {
+    let mut params = params
+        .split([';', ':'])
+        .map(|p| p.parse::<u8>().unwrap_or(0));
+    // `ESC [m` is a reset
+    let mut next = Some(params.next().unwrap_or(0));
+    while let Some(p) = next {
+        style = match p {
+            0 => Style::default(),
+            1 => style.add_modifier(Modifier::BOLD),
+            2 => style.add_modifier(Modifier::DIM),
+            3 => style.add_modifier(Modifier::ITALIC),
+            4 => style.add_modifier(Modifier::UNDERLINED),
+            5 => style.add_modifier(Modifier::SLOW_BLINK),
+            6 => style.add_modifier(Modifier::RAPID_BLINK),
+            7 => style.add_modifier(Modifier::REVERSED),
+            8 => style.add_modifier(Modifier::HIDDEN),
+            9 => style.add_modifier(Modifier::CROSSED_OUT),
+            21 | 22 => style.remove_modifier(Modifier::BOLD | Modifier::DIM),
+            23 => style.remove_modifier(Modifier::ITALIC),
+            24 => style.remove_modifier(Modifier::UNDERLINED),
+            25 => style.remove_modifier(Modifier::SLOW_BLINK | Modifier::RAPID_BLINK),
+            27 => style.remove_modifier(Modifier::REVERSED),
+            28 => style.remove_modifier(Modifier::HIDDEN),
+            29 => style.remove_modifier(Modifier::CROSSED_OUT),
+            30..=37 => style.fg(basic_color(p - 30)),
+            38 => match extended_color(&mut params) {
+                Some(color) => style.fg(color),
+                None => style,
+            },
+            39 => Style { fg: None, ..style },
+            40..=47 => style.bg(basic_color(p - 40)),
+            48 => match extended_color(&mut params) {
+                Some(color) => style.bg(color),
+                None => style,
+            },
+            49 => Style { bg: None, ..style },
+            90..=97 => style.fg(basic_color(p - 90 + 8)),
+            100..=107 => style.bg(basic_color(p - 100 + 8)),
+            _ => style,
+        };
+        next = params.next();
+    }
+    style
+}
+
+/// One of the 16 standard colors, which the terminal's color scheme applies to.
+fn basic_color(i: u8) -> Color {
+    const COLORS: [Color; 16] = [
+        Color::Black,
+        Color::Red,
+        Color::Green,
+        Color::Yellow,
+        Color::Blue,
+        Color::Magenta,
+        Color::Cyan,
+        Color::Gray,
+        Color::DarkGray,
+        Color::LightRed,
+        Color::LightGreen,
+        Color::LightYellow,
+        Color::LightBlue,
+        Color::LightMagenta,
+        Color::LightCyan,
+        Color::White,
+    ];
+    COLORS[i as usize]
+}
+
+/// Parses the rest of a `38;5;n` or `38;2;r;g;b` color.
+fn extended_color(params: &mut impl Iterator<Item = u8>) -> Option<Color> {
+    match params.next()? {
+        5 => Some(Color::Indexed(params.next()?)),
+        2 => Some(Color::Rgb(params.next()?, params.next()?, params.next()?)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(s: &str) -> String {
+        parse(s).into_iter().map(|(c, _)| c).collect()
+    }
+
+    #[test]
+    fn test_sgr() {
+        let cells = parse("\x1b[1;31mFAIL\x1b[0m ok \x1b[38;5;208mx\x1b[38;2;1;2;3my\x1b[39mz");
+        assert_eq!(
+            cells[0],
+            (
+                'F',
+                Style::default().fg(Color::Red).add_modifier(Modifier::BOLD)
+            )
+        );
+        assert_eq!(cells[4], (' ', Style::default()));
+        assert_eq!(cells[8].1.fg, Some(Color::Indexed(208)));
+        assert_eq!(cells[9].1.fg, Some(Color::Rgb(1, 2, 3)));
+        assert_eq!(cells[10].1.fg, None);
+        assert_eq!(text("\x1b[1;31mFAIL\x1b[0m ok"), "FAIL ok");
+    }
+
+    #[test]
+    fn test_cursor_and_erase() {
+        assert_eq!(text("50%\x1b[3Dxx"), "xx%");
+        assert_eq!(text("downloading\x1b[1G\x1b[Kdone"), "done");
+        assert_eq!(text("abc\x08\x08X"), "aXc");
+        assert_eq!(text(" 10%|#  |\r 20%|## |\r100%|###|"), "100%|###|");
+        assert_eq!(text("long line\rshort"), "shortline");
+        assert_eq!(text("a\x1b[3Cb"), "a   b");
+        assert_eq!(
+            text("\x1b]8;;https://x.org\x1b\\link\x1b]8;;\x07 \x1b[?25lok"),
+            "link ok"
+        );
+        assert_eq!(strip("plain"), Cow::Borrowed("plain"));
+    }
+
+    #[test]
+    fn test_cursor_parameter_bounds() {
+        assert_eq!(text("a\x1b[99999999999Cb").len(), MAX_COLUMN + 1);
+        assert_eq!(
+            text("a\x1b[99999999999999999999999Cb").len(),
+            MAX_COLUMN + 1
+        );
+        assert_eq!(text("a\x1b[5000Gb").len(), MAX_COLUMN);
+        assert_eq!(text("a\x1b[4000C\x1b[4000Cb").len(), MAX_COLUMN + 1);
+        assert_eq!(text("abc\x1b[0Gx"), "xbc");
+        assert_eq!(text("abc\x1b[0Dx"), "abx");
+        assert_eq!(text("abc\x1b[2D\x1b[99999999999Px"), "ax");
+        assert_eq!(text("abc\x1b[1G\x1b[99999999999X"), "   ");
+    }
+}
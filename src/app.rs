@@ -1,30 +1,54 @@
 use crossbeam::{
-    channel::{unbounded, Receiver},
+    channel::{unbounded, Receiver, Sender},
     select,
 };
-use itertools::Either;
-use std::{cmp::min, iter::once, path::PathBuf, process::Command};
+use itertools::Itertools;
+use regex::{Regex, RegexBuilder};
+use std::{
+    cmp::min,
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+    process::Command,
+    thread,
+};
 use std::{process::Stdio, time::Duration};
-use fuzzy_matcher::{FuzzyMatcher, skim::SkimMatcherV2};
-
-use crate::file_watcher::{FileWatcherError, FileWatcherHandle};
-use crate::job_watcher::JobWatcherHandle;
 
-use crossterm::event::{Event, KeyCode, KeyEvent, MouseEvent, MouseEventKind, MouseButton};
+use crate::ansi;
+use crate::config::{self, Config, Level, RemoteConfig, SavedFilters};
+use crate::export::{self, JobsFormat};
+use crate::file_watcher::{shell_quote, FileWatcherError, FileWatcherHandle, LogFile};
+use crate::filter::Filter;
+use crate::fuzzy_finder::{self, Column, Highlights, MatchMode, Matcher};
+use crate::grouping::{self, GroupInfo, GroupKey};
+use crate::job_watcher::{expand_nodelist, first_node, JobWatcherHandle, LogSource};
+use crate::log_buffer::{LogBuffer, LogUpdate, SharedLogBuffer};
+use crate::log_view::{fit_text, LogDisplay, LogHighlighter, LogMode, Progress};
+use crate::notifications::{self, JobEvent, Notifier};
+use crate::submit::{self, SubmitField, SubmitForm};
+
+use crossterm::event::{
+    DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEvent, KeyModifiers, MouseButton,
+    MouseEvent, MouseEventKind,
+};
+use crossterm::{
+    execute,
+    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+};
 use ratatui::{
     backend::Backend,
-    layout::{Alignment, Constraint, Direction, Layout, Rect},
+    layout::{Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style},
     text::{Line, Span, Text},
     widgets::{Block, Borders, Cell, Clear, Paragraph, Row, Scrollbar, ScrollbarOrientation, ScrollbarState, Table, TableState, Wrap},
     Frame, Terminal,
 };
-use std::io;
+use std::io::{self, Write};
 
 #[derive(PartialEq)]
 pub enum Focus {
     Jobs,
     FuzzyFinder,
+    Log,
 }
 
 #[derive(Clone)]
@@ -35,9 +59,88 @@ pub enum ViewMode {
 
 pub enum Dialog {
     ConfirmCancelJob(String, bool), // (job_id, is_array_job)
+    ConfirmCancelJobs(Vec<String>),
+    Filter {
+        input: String,
+        error: Option<String>,
+    },
+    /// Naming the filter to save; `replaces` is the filter of that name, once enter was pressed.
+    SaveFilter {
+        filter: String,
+        name: String,
+        replaces: Option<String>,
+    },
+    /// Choosing what to copy to the clipboard, or to save with `save`.
+    Copy {
+        save: bool,
+    },
+    SaveAs {
+        text: String,
+        path: String,
+    },
+    /// Choosing the keys to group the job list by, outermost first.
+    GroupBy(Vec<GroupKey>),
+    /// Exporting the listed jobs to a file, in the format of its extension.
+    Export {
+        path: String,
+        error: Option<String>,
+    },
+    /// Choosing which node of a job to attach a shell on.
+    AttachNode {
+        id: String,
+        nodes: Vec<String>,
+        selected: usize,
+    },
+    Submit(SubmitForm),
+    /// Choosing a recently submitted script for the form.
+    RecentScripts {
+        form: SubmitForm,
+        scripts: Vec<PathBuf>,
+        selected: usize,
+    },
 }
 
+/// What can be copied or saved from the selected job.
 #[derive(Clone, Copy)]
+enum CopyItem {
+    JobId,
+    LogPath,
+    VisibleLines,
+    MatchingLines,
+    Report,
+}
+
+impl CopyItem {
+    const ALL: [CopyItem; 5] = [
+        CopyItem::JobId,
+        CopyItem::LogPath,
+        CopyItem::VisibleLines,
+        CopyItem::MatchingLines,
+        CopyItem::Report,
+    ];
+
+    fn key(self) -> char {
+        match self {
+            CopyItem::JobId => 'i',
+            CopyItem::LogPath => 'p',
+            CopyItem::VisibleLines => 'l',
+            CopyItem::MatchingLines => 'm',
+            CopyItem::Report => 'r',
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            CopyItem::JobId => "job id",
+            CopyItem::LogPath => "log path",
+            CopyItem::VisibleLines => "visible log lines",
+            CopyItem::MatchingLines => "log lines matching the search",
+            CopyItem::Report => "job report (Markdown)",
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
 pub enum ScrollAnchor {
     Top,
     Bottom,
@@ -50,11 +153,62 @@ pub enum OutputFileView {
     Stderr,
 }
 
+/// How the stdout and stderr of the selected job are laid out in the log pane.
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+pub enum LogLayout {
+    /// Only the file chosen with [`OutputFileView`].
+    #[default]
+    Single,
+    /// Both files, the chosen one above the other.
+    Stacked,
+    /// Both files, the chosen one left of the other.
+    SideBySide,
+    /// Both files in one log, stderr in red: what they hold when shown, one after the other, then
+    /// their new lines in the order they are read.
+    Merged,
+}
+
+impl LogLayout {
+    fn next(self) -> Self {
+        match self {
+            LogLayout::Single => LogLayout::Stacked,
+            LogLayout::Stacked => LogLayout::SideBySide,
+            LogLayout::SideBySide => LogLayout::Merged,
+            LogLayout::Merged => LogLayout::Single,
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            LogLayout::Single => "single",
+            LogLayout::Stacked => "stacked",
+            LogLayout::SideBySide => "side by side",
+            LogLayout::Merged => "merged",
+        }
+    }
+}
+
+/// A job whose log is shown in a tile, kept after the job leaves the queue.
+struct PinnedJob {
+    id: String,
+    name: String,
+    stdout: Option<PathBuf>,
+    stderr: Option<PathBuf>,
+    node: Option<String>,
+}
+
 pub struct App {
     focus: Focus,
     dialog: Option<Dialog>,
     view_mode: ViewMode,
+    group_by: Vec<GroupKey>,
+    collapsed_groups: HashSet<String>, // paths of collapsed group rows
+    filter: Option<Filter>,
+    saved_filters: SavedFilters,
+    saved_filters_error: Option<String>,
+    status_message: Option<String>,
     jobs: Vec<Job>,
+    jobs_generation: u64, // of the squeue arguments `jobs` were listed with
     display_jobs: Vec<DisplayJob>,
     original_squeue_args: Vec<String>,
     job_list_state: TableState,
@@ -62,16 +216,56 @@ pub struct App {
     job_list_scrollbar_area: Rect,
     job_list_area: Rect,
     job_output_area: Rect,
-    job_output: Result<String, FileWatcherError>,
+    job_output_secondary_area: Rect,
+    job_output: Result<SharedLogBuffer, FileWatcherError>,
+    /// The other file in the stacked and side by side layouts, scrolled along with `job_output`.
+    job_output_secondary: Option<Result<SharedLogBuffer, FileWatcherError>>,
+    job_output_first_line: usize, // first line kept in `job_output`, which moves with `log.max_lines`
     job_output_anchor: ScrollAnchor,
-    job_output_offset: u16,
+    job_output_offset: usize,
     job_output_wrap: bool,
+    /// Whether the log scrolls to new lines, i.e. is at its tail. While paused, the log is anchored
+    /// at the top so that it stays in place, and counts the lines added since.
+    job_output_follow: bool,
+    job_output_new_lines: usize,
+    job_output_end: usize, // end of `job_output` when the new lines were last counted
+    job_output_job: Option<String>, // id of the job whose log is shown
+    job_output_positions: HashMap<String, (ScrollAnchor, usize, bool)>, // scroll positions of other jobs
+    log_mode: LogMode,
+    // Log search state; `log_search_matches` holds the indices of the matching log lines
+    log_search_active: bool,
+    log_search_input: String,
+    log_search: Option<Regex>,
+    log_search_error: Option<String>,
+    log_search_matches: Vec<usize>,
+    log_search_current: Option<usize>, // index into `log_search_matches`
+    // Lines matching the error/warning highlight rules, for `e`/`E`
+    log_highlighter: LogHighlighter,
+    log_problems: Vec<(usize, Level)>,
+    log_error_count: usize,
+    log_warning_count: usize,
+    log_problem_current: Option<usize>, // index into `log_problems`
+    log_progress: Option<Progress>,
     job_watcher: JobWatcherHandle,
     job_output_watcher: FileWatcherHandle,
-    // sender: Sender<AppMessage>,
+    remote_logs: Option<RemoteConfig>,
+    sender: Sender<AppMessage>,
     receiver: Receiver<AppMessage>,
     input_receiver: Receiver<std::io::Result<Event>>,
+    input_pause: Sender<()>, // pauses the input thread, and resumes it when sent again
+    /// A command to run in place of the TUI, like a pager, and a name for it in messages.
+    suspended_command: Option<(Command, String)>,
+    notifier: Notifier,
+    /// Set by `q` to leave the TUI.
+    quit: bool,
+    /// A job submitted from turf, to select once it's listed.
+    submitted_job: Option<String>,
+    attach_command: String,
     output_file_view: OutputFileView,
+    log_layout: LogLayout,
+    pinned_jobs: Vec<PinnedJob>,
+    show_tiles: bool,
+    tile_outputs: Vec<Result<SharedLogBuffer, FileWatcherError>>, // by index in `pinned_jobs`
     is_dragging_scrollbar: bool,
     split_ratio: u16, // Percentage for left panel (job list)
     is_dragging_resize: bool,
@@ -81,13 +275,14 @@ pub struct App {
     is_dragging_column: bool,
     column_being_resized: Option<usize>,
     column_resize_areas: Vec<Rect>,
-    // Fuzzy finder state
+    // Fuzzy finder state, the table stays narrowed to the matches while the input is not empty
     fuzzy_finder_active: bool,
     fuzzy_finder_input: String,
-    fuzzy_finder_filtered_jobs: Vec<(usize, i64)>, // (original_index, score)
-    fuzzy_finder_selected: usize,
+    fuzzy_finder_mode: MatchMode,
+    fuzzy_finder_error: Option<String>,
 }
 
+#[derive(Clone, Default)]
 pub struct Job {
     pub job_id: String,
     pub array_id: String,
@@ -97,12 +292,17 @@ pub struct Job {
     pub state_compact: String,
     pub reason: Option<String>,
     pub user: String,
+    pub account: String,
     pub time: String,
     pub tres: String,
     pub partition: String,
     pub nodelist: String,
     pub stdout: Option<PathBuf>,
     pub stderr: Option<PathBuf>,
+    pub stdout_error: Option<String>, // why `stdout` couldn't be resolved
+    pub stderr_error: Option<String>,
+    pub stdout_source: Option<LogSource>, // where `stdout` was found if not at its pattern's path
+    pub stderr_source: Option<LogSource>,
     pub command: String,
 }
 
@@ -115,6 +315,7 @@ pub struct DisplayJob {
     pub state_compact: String,
     pub reason: Option<String>,
     pub user: String,
+    pub account: String,
     pub time: String,
     pub tres: String,
     pub partition: String,
@@ -124,10 +325,17 @@ pub struct DisplayJob {
     pub task_count: Option<usize>,
     pub stdout: Option<PathBuf>,
     pub stderr: Option<PathBuf>,
+    pub stdout_error: Option<String>,
+    pub stderr_error: Option<String>,
+    pub stdout_source: Option<LogSource>,
+    pub stderr_source: Option<LogSource>,
+    pub depth: usize,             // nesting level when the list is grouped
+    pub group: Option<GroupInfo>, // set for group header rows
+    pub highlights: Highlights,   // fuzzy finder matches
 }
 
 impl Job {
-    fn id(&self) -> String {
+    pub fn id(&self) -> String {
         match self.array_step.as_ref() {
             Some(array_step) => format!("{}_{}", self.array_id, array_step),
             None => self.job_id.clone(),
@@ -136,7 +344,65 @@ impl Job {
 }
 
 impl DisplayJob {
-    fn id(&self) -> String {
+    pub fn from_job(job: &Job, job_id: String) -> Self {
+        DisplayJob {
+            job_id,
+            array_id: job.array_id.clone(),
+            name: job.name.clone(),
+            state: job.state.clone(),
+            state_compact: job.state_compact.clone(),
+            reason: job.reason.clone(),
+            user: job.user.clone(),
+            account: job.account.clone(),
+            time: job.time.clone(),
+            tres: job.tres.clone(),
+            partition: job.partition.clone(),
+            nodelist: job.nodelist.clone(),
+            command: job.command.clone(),
+            is_array: false,
+            task_count: None,
+            stdout: job.stdout.clone(),
+            stderr: job.stderr.clone(),
+            stdout_error: job.stdout_error.clone(),
+            stderr_error: job.stderr_error.clone(),
+            stdout_source: job.stdout_source.clone(),
+            stderr_source: job.stderr_source.clone(),
+            depth: 0,
+            group: None,
+            highlights: Highlights::default(),
+        }
+    }
+
+    pub fn group(group: GroupInfo, depth: usize) -> Self {
+        DisplayJob {
+            job_id: group.path.clone(),
+            array_id: String::new(),
+            name: group.value.clone(),
+            state: String::new(),
+            state_compact: String::new(),
+            reason: None,
+            user: String::new(),
+            account: String::new(),
+            time: String::new(),
+            tres: String::new(),
+            partition: String::new(),
+            nodelist: String::new(),
+            command: String::new(),
+            is_array: false,
+            task_count: Some(group.job_count),
+            stdout: None,
+            stderr: None,
+            stdout_error: None,
+            stderr_error: None,
+            stdout_source: None,
+            stderr_source: None,
+            depth,
+            group: Some(group),
+            highlights: Highlights::default(),
+        }
+    }
+
+    pub fn id(&self) -> String {
         if self.is_array {
             format!("{}_[1-{}]", self.array_id, self.task_count.unwrap_or(0))
         } else {
@@ -144,6 +410,13 @@ impl DisplayJob {
         }
     }
 
+    pub fn display_id(&self) -> String {
+        match self.task_count {
+            Some(task_count) if self.is_array => format!("{} [{}]", self.array_id, task_count),
+            _ => self.id(),
+        }
+    }
+
     fn cancel_id(&self) -> String {
         if self.is_array {
             // For collapsed array jobs, return the array_id for canceling the entire array
@@ -156,30 +429,56 @@ impl DisplayJob {
 }
 
 pub enum AppMessage {
-    Jobs(Vec<Job>),
-    JobOutput(Result<String, FileWatcherError>),
+    /// The jobs in the queue, with the generation of the squeue arguments they were listed with.
+    Jobs(u64, Vec<Job>),
+    /// The events of running jobs that left the queue, by the state they ended in.
+    JobsEnded(Vec<(JobEvent, Job)>),
+    /// An update of one of the watched log files, by its index in the files shown (0 is the file
+    /// chosen with `o`).
+    JobOutput(usize, Result<LogUpdate, FileWatcherError>),
+    /// The id of the job submitted with the form, or why it couldn't be.
+    Submitted(Box<SubmitForm>, Result<String, String>),
+    /// The form to resubmit a job with, by the job's id, or why it couldn't be filled in.
+    ResubmitForm(String, Result<Box<SubmitForm>, String>),
+    /// An update of the log of a pinned job, by its index in the pinned jobs.
+    TileOutput(usize, Result<LogUpdate, FileWatcherError>),
     Key(KeyEvent),
 }
 
 impl App {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         input_receiver: Receiver<std::io::Result<Event>>,
+        input_pause: Sender<()>,
         slurm_refresh_rate: u64,
         file_refresh_rate: u64,
         squeue_args: Vec<String>,
+        group_by: Vec<GroupKey>,
+        filter: Option<Filter>,
+        config: Config,
+        log_highlighter: LogHighlighter,
+        notifier: Notifier,
     ) -> App {
         let (sender, receiver) = unbounded();
         Self {
             focus: Focus::Jobs,
             dialog: None,
             view_mode: ViewMode::AllJobs,
+            group_by,
+            collapsed_groups: HashSet::new(),
+            filter,
+            saved_filters: config.saved_filters,
+            status_message: config.saved_filters_error.clone(),
+            saved_filters_error: config.saved_filters_error,
             jobs: Vec::new(),
+            jobs_generation: 0,
             display_jobs: Vec::new(),
             original_squeue_args: squeue_args.clone(),
             job_watcher: JobWatcherHandle::new(
                 sender.clone(),
                 Duration::from_secs(slurm_refresh_rate),
                 squeue_args,
+                config.log.search.clone(),
             ),
             job_list_state: {
                 let mut s = TableState::default();
@@ -190,18 +489,51 @@ impl App {
             job_list_scrollbar_area: Rect::default(),
             job_list_area: Rect::default(),
             job_output_area: Rect::default(),
-            job_output: Ok("".to_string()),
+            job_output_secondary_area: Rect::default(),
+            job_output: Ok(LogBuffer::shared(None)),
+            job_output_secondary: None,
+            job_output_first_line: 0,
             job_output_anchor: ScrollAnchor::Bottom,
             job_output_offset: 0,
             job_output_wrap: false,
+            job_output_follow: true,
+            job_output_new_lines: 0,
+            job_output_end: 0,
+            job_output_job: None,
+            job_output_positions: HashMap::new(),
+            log_mode: LogMode::Text,
+            log_search_active: false,
+            log_search_input: String::new(),
+            log_search: None,
+            log_search_error: None,
+            log_search_matches: Vec::new(),
+            log_search_current: None,
+            log_highlighter,
+            log_problems: Vec::new(),
+            log_error_count: 0,
+            log_warning_count: 0,
+            log_problem_current: None,
+            log_progress: None,
             job_output_watcher: FileWatcherHandle::new(
                 sender.clone(),
                 Duration::from_secs(file_refresh_rate),
+                config.log.max_lines,
             ),
-            // sender,
-            receiver: receiver,
-            input_receiver: input_receiver,
+            remote_logs: config.log.remote.clone(),
+            sender,
+            receiver,
+            input_receiver,
+            input_pause,
+            suspended_command: None,
+            notifier,
+            quit: false,
+            submitted_job: None,
+            attach_command: config.attach.command,
             output_file_view: OutputFileView::default(),
+            log_layout: LogLayout::default(),
+            pinned_jobs: Vec::new(),
+            show_tiles: false,
+            tile_outputs: Vec::new(),
             is_dragging_scrollbar: false,
             split_ratio: 30, // Default 30% for job list, 70% for details
             is_dragging_resize: false,
@@ -214,8 +546,8 @@ impl App {
             // Fuzzy finder initialization
             fuzzy_finder_active: false,
             fuzzy_finder_input: String::new(),
-            fuzzy_finder_filtered_jobs: Vec::new(),
-            fuzzy_finder_selected: 0,
+            fuzzy_finder_mode: MatchMode::default(),
+            fuzzy_finder_error: None,
         }
     }
 }
@@ -243,10 +575,13 @@ impl App {
                 recv(self.input_receiver) -> input_res => {
                     match input_res.unwrap().unwrap() {
                         Event::Key(key) => {
-                            if key.code == KeyCode::Char('q') {
+                            self.handle(AppMessage::Key(key));
+                            if self.quit {
                                 return Ok(());
                             }
-                            self.handle(AppMessage::Key(key));
+                            if let Some((command, name)) = self.suspended_command.take() {
+                                self.run_suspended(terminal, command, &name)?;
+                            }
                             needs_redraw = true;
                             is_scrolling = false;
                         },
@@ -288,54 +623,470 @@ impl App {
         }
     }
 
+    /// Runs an interactive command, like a pager, in place of the TUI until it exits.
+    fn run_suspended<B: Backend>(
+        &mut self,
+        terminal: &mut Terminal<B>,
+        mut command: Command,
+        name: &str,
+    ) -> io::Result<()> {
+        // keep the input thread from taking the command's input
+        self.input_pause.send(()).unwrap();
+        disable_raw_mode()?;
+        execute!(io::stdout(), LeaveAlternateScreen, DisableMouseCapture)?;
+        terminal.show_cursor()?;
+
+        let status = command.status();
+
+        enable_raw_mode()?;
+        execute!(io::stdout(), EnterAlternateScreen, EnableMouseCapture)?;
+        terminal.clear()?;
+        self.input_pause.send(()).unwrap();
+        match status {
+            Ok(status) if !status.success() => {
+                self.status_message = Some(format!("{} failed: {}", name, status))
+            }
+            Ok(_) => {}
+            Err(e) => self.status_message = Some(format!("failed to run {}: {}", name, e)),
+        }
+        Ok(())
+    }
+
+    /// Copies `item` of the selected job to the clipboard, or returns the dialog to save it.
+    fn copy(&mut self, item: CopyItem, save: bool) -> Option<Dialog> {
+        let Some(j) = self
+            .job_list_state
+            .selected()
+            .and_then(|i| self.display_jobs.get(i))
+            .filter(|j| j.group.is_none())
+        else {
+            self.status_message = Some("no job selected".to_string());
+            return None;
+        };
+        let (stream, path) = match self.output_file_view {
+            OutputFileView::Stdout => ("stdout", &j.stdout),
+            OutputFileView::Stderr => ("stderr", &j.stderr),
+        };
+        let log = self.job_output.as_ref().ok().map(|log| log.read().unwrap());
+        let (text, file_name) = match item {
+            CopyItem::JobId => (j.id(), format!("{}-id.txt", j.id())),
+            CopyItem::LogPath => (
+                path.as_ref()
+                    .map(|p| p.display().to_string())
+                    .unwrap_or_default(),
+                format!("{}-path.txt", j.id()),
+            ),
+            CopyItem::VisibleLines => {
+                let top = self.log_top_line();
+                let visible_lines = self.job_output_area.height.saturating_sub(2) as usize;
+                (
+                    log.map(|log| export::plain_lines(log.range(top, top + visible_lines)))
+                        .unwrap_or_default(),
+                    format!("{}-{}.log", j.id(), stream),
+                )
+            }
+            CopyItem::MatchingLines => (
+                log.map(|log| {
+                    export::plain_lines(
+                        self.log_search_matches
+                            .iter()
+                            .flat_map(|&l| log.range(l, l + 1)),
+                    )
+                })
+                .unwrap_or_default(),
+                format!("{}-matches.log", j.id()),
+            ),
+            CopyItem::Report => (
+                export::job_report(
+                    j,
+                    stream,
+                    log.as_ref()
+                        .map(|log| log.range(0, usize::MAX))
+                        .into_iter()
+                        .flatten(),
+                ),
+                format!("{}-report.md", j.id()),
+            ),
+        };
+        if text.is_empty() {
+            self.status_message = Some(format!("no {} to copy", item.label()));
+            return None;
+        }
+        if save {
+            return Some(Dialog::SaveAs {
+                text,
+                path: file_name,
+            });
+        }
+        let mut stdout = io::stdout();
+        self.status_message = Some(
+            match stdout
+                .write_all(export::osc52(&text).as_bytes())
+                .and_then(|_| stdout.flush())
+            {
+                Ok(()) => format!("Copied {} to the clipboard", item.label()),
+                Err(e) => format!("Failed to copy {}: {}", item.label(), e),
+            },
+        );
+        None
+    }
+
+    /// Writes the jobs of the job list rows to `path`, and returns how many.
+    fn export_jobs(&self, path: &str) -> Result<usize, String> {
+        let format =
+            JobsFormat::from_path(Path::new(path)).ok_or("use a .json, .csv or .tsv file")?;
+        let jobs = self.exported_jobs();
+        std::fs::write(path, export::jobs(jobs.iter().copied(), format))
+            .map_err(|e| e.to_string())?;
+        Ok(jobs.len())
+    }
+
+    /// The listed jobs that match the search.
+    fn narrowed_jobs(&self) -> Vec<&Job> {
+        // an invalid search doesn't narrow the job list either
+        let matcher = Some(&self.fuzzy_finder_input)
+            .filter(|input| !input.is_empty())
+            .and_then(|input| Matcher::new(input, self.fuzzy_finder_mode).ok());
+        let listed = self.listed_jobs();
+        match &matcher {
+            Some(matcher) => fuzzy_finder::narrow(&listed, matcher),
+            None => listed,
+        }
+    }
+
+    /// The listed jobs in the job list's order, with the collapsed groups expanded.
+    fn exported_jobs(&self) -> Vec<&Job> {
+        let listed = self.narrowed_jobs();
+        match self.view_mode {
+            ViewMode::AllJobs => export::row_jobs(
+                &grouping::group_jobs(&listed, &self.group_by, &HashSet::new()),
+                &listed,
+            ),
+            ViewMode::ArrayJobDetails(_) => listed,
+        }
+    }
+
+    /// Opens the log of the selected job in `$PAGER`, or in `$VISUAL`/`$EDITOR` with `editor`.
+    fn open_log_externally(&mut self, editor: bool) {
+        let file = self
+            .job_list_state
+            .selected()
+            .and_then(|i| self.display_jobs.get(i))
+            .and_then(|j| match (&self.output_file_view, self.log_layout) {
+                (OutputFileView::Stdout, _) | (_, LogLayout::Merged) => j.stdout.clone(),
+                (OutputFileView::Stderr, _) => j.stderr.clone(),
+            });
+        let Some(file) = file else {
+            self.status_message = Some("no log file".to_string());
+            return;
+        };
+        let (var, program) = if editor {
+            let var = if std::env::var_os("VISUAL").is_some() {
+                "VISUAL"
+            } else {
+                "EDITOR"
+            };
+            (var, std::env::var(var).unwrap_or_else(|_| "vi".to_string()))
+        } else {
+            (
+                "PAGER",
+                std::env::var("PAGER").unwrap_or_else(|_| "less".to_string()),
+            )
+        };
+        // through the shell, since the variables can hold arguments, like `less +F`
+        let mut command = Command::new("sh");
+        command
+            .arg("-c")
+            .arg(format!("{} \"$1\"", program))
+            .arg("sh")
+            .arg(file);
+        self.suspended_command = Some((command, format!("${}", var)));
+    }
+
+    /// Attaches a shell to the selected running job, after asking on which node if it has several.
+    fn attach(&mut self) {
+        let Some(j) = self
+            .job_list_state
+            .selected()
+            .and_then(|i| self.display_jobs.get(i))
+            .filter(|j| j.group.is_none() && !j.is_array)
+        else {
+            self.status_message = Some("no job selected".to_string());
+            return;
+        };
+        if j.state_compact != "R" {
+            self.status_message = Some(format!("{} isn't running", j.id()));
+            return;
+        }
+        let id = j.id();
+        let nodes = expand_nodelist(&j.nodelist);
+        match nodes.as_slice() {
+            // rather than running the command with an empty node
+            [] if self.attach_command.contains("{node}") => {
+                self.status_message = Some(format!("no node known for {}", id));
+            }
+            [] => self.attach_to(&id, ""),
+            [node] => self.attach_to(&id, node),
+            _ => {
+                self.dialog = Some(Dialog::AttachNode {
+                    id,
+                    nodes,
+                    selected: 0,
+                })
+            }
+        }
+    }
+
+    fn attach_to(&mut self, id: &str, node: &str) {
+        let command = self
+            .attach_command
+            .replace("{id}", &shell_quote(id))
+            .replace("{node}", &shell_quote(node));
+        let mut shell = Command::new("sh");
+        shell.arg("-c").arg(command);
+        self.suspended_command = Some((shell, format!("shell on {}", id)));
+    }
+
+    /// Submits the form in the background, since sbatch can take a while on a busy cluster.
+    fn submit(&mut self, mut form: SubmitForm) -> Option<Dialog> {
+        let command_line = match form.command_line() {
+            Ok(command_line) => command_line,
+            Err(e) => {
+                form.error = Some(e);
+                return Some(Dialog::Submit(form));
+            }
+        };
+        self.status_message = Some(format!(
+            "submitting {}...",
+            form.value(SubmitField::Script).trim()
+        ));
+        let sender = self.sender.clone();
+        thread::spawn(move || {
+            let result = submit::submit(&command_line);
+            let _ = sender.send(AppMessage::Submitted(Box::new(form), result));
+        });
+        None
+    }
+
+    /// Opens the submit form filled in with what the selected job was submitted with.
+    fn resubmit(&mut self) {
+        let Some(j) = self
+            .job_list_state
+            .selected()
+            .and_then(|i| self.display_jobs.get(i))
+            .filter(|j| j.group.is_none() && !j.is_array)
+        else {
+            self.status_message = Some("no job selected".to_string());
+            return;
+        };
+        // in the background, like submitting, since scontrol can take a while on a busy cluster
+        let id = j.job_id.clone();
+        self.status_message = Some(format!("asking scontrol about {}...", id));
+        let sender = self.sender.clone();
+        thread::spawn(move || {
+            let form = submit::job_form(&id).map(Box::new);
+            let _ = sender.send(AppMessage::ResubmitForm(id, form));
+        });
+    }
+
+    /// Selects the job submitted from turf once it shows up in the queue. A job hidden by the
+    /// filter isn't waited for.
+    fn select_submitted_job(&mut self) {
+        let Some(id) = &self.submitted_job else {
+            return;
+        };
+        if !self
+            .jobs
+            .iter()
+            .any(|j| j.job_id == *id || j.array_id == *id)
+        {
+            return;
+        }
+        if let Some(i) = self
+            .display_jobs
+            .iter()
+            .position(|j| j.group.is_none() && (j.job_id == *id || j.array_id == *id))
+        {
+            self.job_list_state.select(Some(i));
+        }
+        self.submitted_job = None;
+    }
+
+    fn notify(&mut self, event: JobEvent, job: &Job) {
+        if self.notifier.notify(event, job) {
+            self.status_message = Some(format!("job {} ({}) {}", job.id(), job.name, event));
+        }
+    }
+
     fn handle(&mut self, msg: AppMessage) {
         match msg {
-            AppMessage::Jobs(jobs) => {
+            AppMessage::Jobs(generation, jobs) => {
+                // listed with squeue arguments that changed since
+                if generation != self.job_watcher.generation() {
+                    return;
+                }
+                let previous = std::mem::take(&mut self.jobs);
+                let (events, ended) = notifications::job_events(
+                    (self.jobs_generation, &previous),
+                    (generation, &jobs),
+                );
+                for (event, job) in events {
+                    self.notify(event, job);
+                }
+                if !ended.is_empty() {
+                    // sacct can take a while on a busy cluster
+                    let ended: Vec<Job> = ended.into_iter().cloned().collect();
+                    let sender = self.sender.clone();
+                    thread::spawn(move || {
+                        let events = ended
+                            .into_iter()
+                            .map(|j| (notifications::ended_event(&j), j))
+                            .collect();
+                        let _ = sender.send(AppMessage::JobsEnded(events));
+                    });
+                }
                 self.jobs = jobs;
+                self.jobs_generation = generation;
                 self.update_display_jobs();
+                self.select_submitted_job();
                 self.update_job_list_scrollbar();
             },
-            AppMessage::JobOutput(content) => self.job_output = content,
+            AppMessage::JobsEnded(events) => {
+                for (event, job) in events {
+                    self.notify(event, &job);
+                }
+            }
+            AppMessage::Submitted(mut form, result) => match result {
+                Ok(id) => {
+                    let script = PathBuf::from(form.value(SubmitField::Script).trim());
+                    let _ =
+                        config::add_recent_script(std::path::absolute(&script).unwrap_or(script));
+                    self.status_message = Some(format!("submitted job {}", id));
+                    self.submitted_job = Some(id);
+                }
+                Err(e) => {
+                    self.status_message = None;
+                    form.error = Some(e);
+                    self.dialog = Some(Dialog::Submit(*form));
+                }
+            },
+            AppMessage::ResubmitForm(id, form) => match form {
+                Ok(form) => {
+                    self.status_message = None;
+                    self.dialog = Some(Dialog::Submit(*form));
+                }
+                Err(e) => self.status_message = Some(format!("can't resubmit {}: {}", id, e)),
+            },
+            AppMessage::TileOutput(i, update) => {
+                if let Some(tile) = self.tile_outputs.get_mut(i) {
+                    match update {
+                        Ok(LogUpdate::Opened(log)) => *tile = Ok(log),
+                        Ok(LogUpdate::Appended(_)) => {}
+                        Err(e) => *tile = Err(e),
+                    }
+                }
+            }
+            AppMessage::JobOutput(1, update) => match update {
+                // the secondary log isn't searched, so only needs to be redrawn
+                Ok(LogUpdate::Opened(log)) => self.job_output_secondary = Some(Ok(log)),
+                Ok(LogUpdate::Appended(_)) => {}
+                Err(e) => self.job_output_secondary = Some(Err(e)),
+            },
+            AppMessage::JobOutput(_, update) => match update {
+                Ok(LogUpdate::Opened(log)) => {
+                    self.job_output_first_line = log.read().unwrap().first_line();
+                    self.job_output = Ok(log);
+                    self.update_log_caches(0);
+                }
+                Ok(LogUpdate::Appended(from)) => self.update_log_caches(from),
+                Err(e) => {
+                    self.job_output = Err(e);
+                    self.update_log_caches(0);
+                }
+            },
             AppMessage::Key(key) => {
-                if let Some(dialog) = &self.dialog {
-                    match dialog {
-                        Dialog::ConfirmCancelJob(id, _) => match key.code {
-                            KeyCode::Enter | KeyCode::Char('y') => {
-                                Command::new("scancel")
-                                    .arg(id)
-                                    .stdout(Stdio::null())
-                                    .stderr(Stdio::null())
-                                    .spawn()
-                                    .expect("failed to execute scancel");
-                                self.dialog = None;
-                            }
-                            KeyCode::Esc => {
-                                self.dialog = None;
-                            }
-                            _ => {}
-                        },
-                    };
+                self.status_message = None;
+                let position = (self.job_output_anchor, self.job_output_offset);
+                if let Some(dialog) = self.dialog.take() {
+                    self.handle_dialog_key(dialog, key);
+                } else if self.log_search_active {
+                    match key.code {
+                        KeyCode::Enter => {
+                            self.log_search_active = false;
+                            self.log_search_next(true);
+                        }
+                        KeyCode::Esc => {
+                            self.log_search_active = false;
+                            self.log_search_input.clear();
+                            self.update_log_search();
+                        }
+                        KeyCode::Backspace => {
+                            self.log_search_input.pop();
+                            self.update_log_search();
+                        }
+                        KeyCode::Char(c) => {
+                            self.log_search_input.push(c);
+                            self.update_log_search();
+                        }
+                        _ => {}
+                    }
                 } else if self.fuzzy_finder_active {
                     // Handle fuzzy finder input only
                     match key.code {
-                        KeyCode::Up => self.fuzzy_finder_previous(),
-                        KeyCode::Down => self.fuzzy_finder_next(),
-                        KeyCode::Enter => self.fuzzy_finder_select(),
-                        KeyCode::Esc => self.deactivate_fuzzy_finder(),
-                        KeyCode::Backspace => self.fuzzy_finder_backspace(),
-                        KeyCode::Char(c) => self.fuzzy_finder_input(c),
+                        KeyCode::Up => self.select_previous_job(),
+                        KeyCode::Down => self.select_next_job(),
+                        KeyCode::Enter => self.deactivate_fuzzy_finder(),
+                        KeyCode::Esc => self.clear_fuzzy_finder(),
+                        KeyCode::Tab => {
+                            self.fuzzy_finder_mode = self.fuzzy_finder_mode.next();
+                            self.update_fuzzy_finder();
+                        }
+                        KeyCode::Backspace => {
+                            self.fuzzy_finder_input.pop();
+                            self.update_fuzzy_finder();
+                        }
+                        KeyCode::Char('x') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                            self.confirm_cancel_listed_jobs()
+                        }
+                        KeyCode::Char('e') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                            self.enter_selected()
+                        }
+                        KeyCode::Char(c) => {
+                            self.fuzzy_finder_input.push(c);
+                            self.update_fuzzy_finder();
+                        }
                         _ => {}
                     }
                 } else {
                     // Handle normal application input only
                     match key.code {
+                        // only here, since `q` can be typed in dialogs and searches
+                        KeyCode::Char('q') => self.quit = true,
+                        KeyCode::Char('/') if self.focus == Focus::Log => {
+                            self.log_search_active = true;
+                        }
                         KeyCode::Char('/') => {
                             self.activate_fuzzy_finder();
-                        },
+                        }
                         KeyCode::Char('h') | KeyCode::Left => self.focus_previous_panel(),
                         KeyCode::Char('l') | KeyCode::Right => self.focus_next_panel(),
+                        KeyCode::Char('k') | KeyCode::Up if self.focus == Focus::Log => {
+                            self.scroll_job_output_by(-1)
+                        }
+                        KeyCode::Char('j') | KeyCode::Down if self.focus == Focus::Log => {
+                            self.scroll_job_output_by(1)
+                        }
                         KeyCode::Char('k') | KeyCode::Up => self.select_previous_job(),
                         KeyCode::Char('j') | KeyCode::Down => self.select_next_job(),
+                        KeyCode::Char('n') if self.log_search.is_some() => {
+                            self.log_search_next(true)
+                        }
+                        KeyCode::Char('N') if self.log_search.is_some() => {
+                            self.log_search_next(false)
+                        }
+                        KeyCode::Char('x') => self.log_mode = self.log_mode.next(),
+                        KeyCode::Char('e') => self.log_problem_next(true),
+                        KeyCode::Char('E') => self.log_problem_next(false),
                         KeyCode::PageDown => {
                             let delta = if key.modifiers.intersects(
                                 crossterm::event::KeyModifiers::SHIFT
@@ -382,18 +1133,49 @@ impl App {
                             self.job_output_offset = 0;
                             self.job_output_anchor = ScrollAnchor::Top;
                         }
-                        KeyCode::End => {
-                            self.job_output_offset = 0;
-                            self.job_output_anchor = ScrollAnchor::Bottom;
-                        }
+                        KeyCode::End | KeyCode::Char('G') => self.follow_log(),
+                        KeyCode::Char('t') if self.job_output_follow => self.pause_log(),
+                        KeyCode::Char('t') => self.follow_log(),
                         KeyCode::Char('c') => {
                             if let Some(selected_idx) = self.job_list_state.selected() {
-                                if let Some(display_job) = self.display_jobs.get(selected_idx) {
+                                if let Some(display_job) = self
+                                    .display_jobs
+                                    .get(selected_idx)
+                                    .filter(|j| j.group.is_none())
+                                {
                                     let cancel_id = display_job.cancel_id();
-                                    self.dialog = Some(Dialog::ConfirmCancelJob(cancel_id, display_job.is_array));
+                                    self.dialog = Some(Dialog::ConfirmCancelJob(
+                                        cancel_id,
+                                        display_job.is_array,
+                                    ));
                                 }
                             }
                         }
+                        KeyCode::Char('C') => self.confirm_cancel_listed_jobs(),
+                        KeyCode::Char('y') => self.dialog = Some(Dialog::Copy { save: false }),
+                        KeyCode::Char('Y') => self.dialog = Some(Dialog::Copy { save: true }),
+                        KeyCode::Char('X') => {
+                            self.dialog = Some(Dialog::Export {
+                                path: "jobs.csv".to_string(),
+                                error: None,
+                            })
+                        }
+                        KeyCode::Char('v') => self.open_log_externally(false),
+                        KeyCode::Char('V') => self.open_log_externally(true),
+                        KeyCode::Char('a') => self.attach(),
+                        KeyCode::Char('b') => {
+                            self.dialog = Some(Dialog::Submit(SubmitForm::default()))
+                        }
+                        KeyCode::Char('r') => self.resubmit(),
+                        KeyCode::Char('p') => self.toggle_pinned(),
+                        KeyCode::Char('P') => {
+                            if self.pinned_jobs.is_empty() {
+                                self.status_message =
+                                    Some("no pinned jobs, pin jobs with p".to_string());
+                            } else {
+                                self.show_tiles = !self.show_tiles;
+                            }
+                        }
                         KeyCode::Char('o') => {
                             self.output_file_view = match self.output_file_view {
                                 OutputFileView::Stdout => OutputFileView::Stderr,
@@ -403,100 +1185,643 @@ impl App {
                         KeyCode::Char('w') => {
                             self.job_output_wrap = !self.job_output_wrap;
                         }
-                        KeyCode::Enter => self.enter_array_job(),
+                        KeyCode::Char('s') => {
+                            self.log_layout = self.log_layout.next();
+                            self.status_message =
+                                Some(format!("log layout: {}", self.log_layout.label()));
+                        }
+                        KeyCode::Char('g') => {
+                            self.dialog = Some(Dialog::GroupBy(self.group_by.clone()))
+                        }
+                        KeyCode::Char('f') => {
+                            self.dialog = Some(Dialog::Filter {
+                                input: self
+                                    .filter
+                                    .as_ref()
+                                    .map(|f| f.source().to_owned())
+                                    .unwrap_or_default(),
+                                error: None,
+                            });
+                        }
+                        KeyCode::Char('F') => self.set_filter(None),
+                        KeyCode::Enter => self.enter_selected(),
                         KeyCode::Esc => {
-                            if matches!(self.view_mode, ViewMode::ArrayJobDetails(_)) {
+                            if !self.fuzzy_finder_input.is_empty() {
+                                self.clear_fuzzy_finder();
+                            } else if matches!(self.view_mode, ViewMode::ArrayJobDetails(_)) {
                                 self.exit_array_job();
                             }
                         },
                         _ => {}
                     };
                 }
+                self.update_log_follow(position);
             }
         }
 
         // update
-        self.job_output_watcher
-            .set_file_path(self.job_list_state.selected().and_then(|i| {
-                self.display_jobs.get(i).and_then(|j| match self.output_file_view {
-                    OutputFileView::Stdout => j.stdout.clone(),
-                    OutputFileView::Stderr => j.stderr.clone(),
-                })
-            }));
-    }
-
-    fn ui(&mut self, f: &mut Frame) {
-        // Store terminal width for resize calculations
-        self.terminal_width = f.area().width;
-        
-        // Layout
+        let job = self
+            .job_list_state
+            .selected()
+            .and_then(|i| self.display_jobs.get(i))
+            .map(|j| j.id());
+        if job != self.job_output_job {
+            if let Some(previous) = self.job_output_job.take() {
+                self.job_output_positions.insert(
+                    previous,
+                    (
+                        self.job_output_anchor,
+                        self.job_output_offset,
+                        self.job_output_follow,
+                    ),
+                );
+            }
+            (
+                self.job_output_anchor,
+                self.job_output_offset,
+                self.job_output_follow,
+            ) = job
+                .as_ref()
+                .and_then(|j| self.job_output_positions.get(j).copied())
+                .unwrap_or((ScrollAnchor::Bottom, 0, true));
+            self.job_output_new_lines = 0;
+            self.job_output_job = job;
+        }
 
-        let content_help = Layout::default()
-            .direction(Direction::Vertical)
-            .constraints([Constraint::Min(3), Constraint::Length(1)].as_ref())
-            .split(f.area());
+        let (files, merged) = self.log_files();
+        if files.len() < 2 || merged {
+            self.job_output_secondary = None;
+        }
+        self.job_output_watcher.set_files(files, merged);
 
-        let master_detail = Layout::default()
-            .direction(Direction::Horizontal)
-            .constraints([Constraint::Percentage(self.split_ratio), Constraint::Percentage(100 - self.split_ratio)].as_ref())
-            .split(content_help[0]);
+        let tile_files = self.tile_files();
+        self.tile_outputs
+            .resize_with(tile_files.len(), || Ok(LogBuffer::shared(None)));
+        self.job_output_watcher.set_tile_files(tile_files);
+    }
 
-        // Split the job list area to make room for scrollbar
-        let job_area_with_scrollbar = Layout::default()
-            .direction(Direction::Horizontal)
-            .constraints([Constraint::Length(1), Constraint::Min(0)].as_ref())
-            .split(master_detail[0]);
+    /// Pins the selected job, or unpins it if it's pinned.
+    fn toggle_pinned(&mut self) {
+        let Some(j) = self
+            .job_list_state
+            .selected()
+            .and_then(|i| self.display_jobs.get(i))
+            .filter(|j| j.group.is_none())
+        else {
+            return;
+        };
+        let id = j.id();
+        if let Some(i) = self.pinned_jobs.iter().position(|p| p.id == id) {
+            self.pinned_jobs.remove(i);
+            // the tiles after it move, and are read again
+            self.tile_outputs.clear();
+            self.status_message = Some(format!("unpinned {}", id));
+        } else if j.stdout.is_none() && j.stderr.is_none() {
+            self.status_message = Some(format!("{} has no log file", id));
+        } else {
+            self.pinned_jobs.push(PinnedJob {
+                id: id.clone(),
+                name: j.name.clone(),
+                stdout: j.stdout.clone(),
+                stderr: j.stderr.clone(),
+                node: first_node(&j.nodelist),
+            });
+            self.status_message = Some(format!(
+                "pinned {} ({} pinned, P to show tiles)",
+                id,
+                self.pinned_jobs.len()
+            ));
+        }
+        if self.pinned_jobs.is_empty() {
+            self.show_tiles = false;
+        }
+    }
 
-        let job_detail_log = Layout::default()
-            .direction(Direction::Vertical)
-            .constraints([Constraint::Length(7), Constraint::Min(3)].as_ref())
-            .split(master_detail[1]);
+    /// The files shown in the tiles, the one chosen with `o` for each pinned job if it has it.
+    /// None while the tiles are hidden, so that they aren't read for nothing.
+    fn tile_files(&self) -> Vec<LogFile> {
+        if !self.show_tiles {
+            return Vec::new();
+        }
+        self.pinned_jobs
+            .iter()
+            .filter_map(|p| {
+                let path = match self.output_file_view {
+                    OutputFileView::Stdout => p.stdout.as_ref().or(p.stderr.as_ref()),
+                    OutputFileView::Stderr => p.stderr.as_ref().or(p.stdout.as_ref()),
+                }?;
+                Some(self.log_file(path, &p.id, p.node.as_deref()))
+            })
+            .collect()
+    }
 
-        // Help
-        let help_options = if self.fuzzy_finder_active {
-            vec![
-                ("type", "search"),
-                ("⏶/⏷", "navigate"),
-                ("enter", "select"),
-                ("esc", "cancel"),
-            ]
-        } else {
-            match &self.view_mode {
-                ViewMode::AllJobs => vec![
-                    ("q", "quit"),
-                    ("/", "fuzzy find"),
-                    ("⏶/⏷", "navigate"),
-                    ("enter", "expand array"),
-                    ("c", "cancel job"),
-                    ("o", "toggle stdout/stderr"),
-                    ("w", "toggle text wrap"),
-                ],
-                ViewMode::ArrayJobDetails(_) => vec![
-                    ("q", "quit"),
-                    ("/", "fuzzy find"),
-                    ("⏶/⏷", "navigate"),
-                    ("esc", "back to jobs"),
-                    ("c", "cancel job"),
-                    ("o", "toggle stdout/stderr"),
-                    ("w", "toggle text wrap"),
-                ],
-            }
+    /// The files of the selected job to show with the current layout, the one chosen with `o`
+    /// first, and whether to merge them.
+    fn log_files(&self) -> (Vec<LogFile>, bool) {
+        let Some(j) = self
+            .job_list_state
+            .selected()
+            .and_then(|i| self.display_jobs.get(i))
+        else {
+            return (Vec::new(), false);
         };
-        let blue_style = Style::default().fg(Color::Blue);
-        let light_blue_style = Style::default().fg(Color::LightBlue);
+        let (primary, secondary) = match self.output_file_view {
+            OutputFileView::Stdout => (&j.stdout, &j.stderr),
+            OutputFileView::Stderr => (&j.stderr, &j.stdout),
+        };
+        let (primary, secondary, merged) = match self.log_layout {
+            LogLayout::Single => (primary, &None, false),
+            LogLayout::Stacked | LogLayout::SideBySide => (primary, secondary, false),
+            // stdout first, so that stderr is the one colored
+            LogLayout::Merged => (&j.stdout, &j.stderr, true),
+        };
+        let mut paths: Vec<&PathBuf> = primary.iter().collect();
+        // a job with stderr in its stdout file has nothing else to show
+        paths.extend(
+            secondary
+                .iter()
+                .filter(|s| !primary.is_none() && Some(*s) != primary.as_ref()),
+        );
+        let node = first_node(&j.nodelist);
+        let files = paths
+            .into_iter()
+            .map(|p| self.log_file(p, &j.id(), node.as_deref()))
+            .collect();
+        (files, merged)
+    }
 
-        let help = Line::from(help_options.iter().fold(
-            Vec::new(),
-            |mut acc, (key, description)| {
-                if !acc.is_empty() {
-                    acc.push(Span::raw(" | "));
-                }
-                acc.push(Span::styled(*key, blue_style));
-                acc.push(Span::raw(": "));
-                acc.push(Span::styled(*description, light_blue_style));
-                acc
-            },
-        ));
+    fn log_file(&self, path: &Path, id: &str, node: Option<&str>) -> LogFile {
+        LogFile::new(path, id, node, self.remote_logs.as_ref())
+    }
+
+    fn handle_dialog_key(&mut self, dialog: Dialog, key: KeyEvent) {
+        self.dialog = match dialog {
+            Dialog::Copy { save } => match key.code {
+                KeyCode::Char(c) => match CopyItem::ALL.into_iter().find(|item| item.key() == c) {
+                    Some(item) => self.copy(item, save),
+                    None => Some(Dialog::Copy { save }),
+                },
+                _ => None,
+            },
+            Dialog::SaveAs { text, mut path } => match key.code {
+                KeyCode::Char(c) => {
+                    path.push(c);
+                    Some(Dialog::SaveAs { text, path })
+                }
+                KeyCode::Backspace => {
+                    path.pop();
+                    Some(Dialog::SaveAs { text, path })
+                }
+                KeyCode::Enter if !path.is_empty() => {
+                    self.status_message = Some(match std::fs::write(&path, &text) {
+                        Ok(()) => format!("Saved to {}", path),
+                        Err(e) => format!("Failed to save to {}: {}", path, e),
+                    });
+                    None
+                }
+                KeyCode::Esc => None,
+                _ => Some(Dialog::SaveAs { text, path }),
+            },
+            Dialog::GroupBy(mut keys) => match key.code {
+                KeyCode::Char(c) => {
+                    if let Some(k) = GroupKey::ALL.into_iter().find(|k| k.key() == c) {
+                        // a key that's already used is removed, so its level can be moved
+                        match keys.iter().position(|&used| used == k) {
+                            Some(i) => {
+                                keys.remove(i);
+                            }
+                            None => keys.push(k),
+                        }
+                    }
+                    Some(Dialog::GroupBy(keys))
+                }
+                KeyCode::Backspace => {
+                    keys.pop();
+                    Some(Dialog::GroupBy(keys))
+                }
+                KeyCode::Enter => {
+                    self.set_group_by(keys);
+                    None
+                }
+                KeyCode::Esc => None,
+                _ => Some(Dialog::GroupBy(keys)),
+            },
+            Dialog::Export { mut path, error } => match key.code {
+                KeyCode::Char(c) => {
+                    path.push(c);
+                    Some(Dialog::Export { path, error: None })
+                }
+                KeyCode::Backspace => {
+                    path.pop();
+                    Some(Dialog::Export { path, error: None })
+                }
+                KeyCode::Enter if !path.is_empty() => match self.export_jobs(&path) {
+                    Ok(count) => {
+                        self.status_message = Some(format!("Exported {} jobs to {}", count, path));
+                        None
+                    }
+                    Err(e) => Some(Dialog::Export {
+                        path,
+                        error: Some(e),
+                    }),
+                },
+                KeyCode::Esc => None,
+                _ => Some(Dialog::Export { path, error }),
+            },
+            Dialog::Submit(mut form) => match key.code {
+                KeyCode::Char('r') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    match config::load_recent_scripts() {
+                        Ok(scripts) if !scripts.is_empty() => Some(Dialog::RecentScripts {
+                            form,
+                            scripts,
+                            selected: 0,
+                        }),
+                        Ok(_) => {
+                            form.error = Some("no recent scripts".to_string());
+                            Some(Dialog::Submit(form))
+                        }
+                        Err(e) => {
+                            form.error = Some(format!("failed to load recent scripts: {}", e));
+                            Some(Dialog::Submit(form))
+                        }
+                    }
+                }
+                KeyCode::Char(c) => {
+                    form.focused().push(c);
+                    Some(Dialog::Submit(form))
+                }
+                KeyCode::Backspace => {
+                    form.focused().pop();
+                    Some(Dialog::Submit(form))
+                }
+                KeyCode::Tab | KeyCode::Down => {
+                    form.focus = (form.focus + 1) % SubmitField::ALL.len();
+                    Some(Dialog::Submit(form))
+                }
+                KeyCode::BackTab | KeyCode::Up => {
+                    form.focus = (form.focus + SubmitField::ALL.len() - 1) % SubmitField::ALL.len();
+                    Some(Dialog::Submit(form))
+                }
+                KeyCode::Enter => self.submit(form),
+                KeyCode::Esc => None,
+                _ => Some(Dialog::Submit(form)),
+            },
+            Dialog::RecentScripts {
+                mut form,
+                scripts,
+                selected,
+            } => match key.code {
+                KeyCode::Up | KeyCode::Char('k') => Some(Dialog::RecentScripts {
+                    form,
+                    scripts,
+                    selected: selected.saturating_sub(1),
+                }),
+                KeyCode::Down | KeyCode::Char('j') => {
+                    let selected = min(selected + 1, scripts.len() - 1);
+                    Some(Dialog::RecentScripts {
+                        form,
+                        scripts,
+                        selected,
+                    })
+                }
+                KeyCode::Enter => {
+                    form.set(SubmitField::Script, scripts[selected].display().to_string());
+                    form.error = None;
+                    Some(Dialog::Submit(form))
+                }
+                KeyCode::Esc => Some(Dialog::Submit(form)),
+                _ => Some(Dialog::RecentScripts {
+                    form,
+                    scripts,
+                    selected,
+                }),
+            },
+            Dialog::AttachNode {
+                id,
+                nodes,
+                selected,
+            } => match key.code {
+                KeyCode::Up | KeyCode::Char('k') => Some(Dialog::AttachNode {
+                    id,
+                    selected: selected.saturating_sub(1),
+                    nodes,
+                }),
+                KeyCode::Down | KeyCode::Char('j') => {
+                    let selected = min(selected + 1, nodes.len() - 1);
+                    Some(Dialog::AttachNode {
+                        id,
+                        nodes,
+                        selected,
+                    })
+                }
+                KeyCode::Enter => {
+                    self.attach_to(&id, &nodes[selected]);
+                    None
+                }
+                KeyCode::Esc => None,
+                _ => Some(Dialog::AttachNode {
+                    id,
+                    nodes,
+                    selected,
+                }),
+            },
+            Dialog::ConfirmCancelJob(id, is_array) => match key.code {
+                KeyCode::Enter | KeyCode::Char('y') => {
+                    scancel(std::slice::from_ref(&id));
+                    None
+                }
+                KeyCode::Esc => None,
+                _ => Some(Dialog::ConfirmCancelJob(id, is_array)),
+            },
+            Dialog::ConfirmCancelJobs(ids) => match key.code {
+                KeyCode::Enter | KeyCode::Char('y') => {
+                    scancel(&ids);
+                    None
+                }
+                KeyCode::Esc => None,
+                _ => Some(Dialog::ConfirmCancelJobs(ids)),
+            },
+            Dialog::Filter { mut input, error } => match key.code {
+                KeyCode::Char('s') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    Some(Dialog::SaveFilter {
+                        filter: input,
+                        name: String::new(),
+                        replaces: None,
+                    })
+                }
+                KeyCode::Char(c) => {
+                    input.push(c);
+                    Some(Dialog::Filter { input, error: None })
+                }
+                KeyCode::Backspace => {
+                    input.pop();
+                    Some(Dialog::Filter { input, error: None })
+                }
+                KeyCode::Enter => match self.parse_filter(&input) {
+                    Ok(filter) => {
+                        self.set_filter(filter);
+                        None
+                    }
+                    Err(e) => Some(Dialog::Filter {
+                        input,
+                        error: Some(e),
+                    }),
+                },
+                KeyCode::Esc => None,
+                _ => Some(Dialog::Filter { input, error }),
+            },
+            Dialog::SaveFilter {
+                filter,
+                mut name,
+                replaces,
+            } => match key.code {
+                KeyCode::Char(c) if !c.is_whitespace() => {
+                    name.push(c);
+                    Some(Dialog::SaveFilter {
+                        filter,
+                        name,
+                        replaces: None,
+                    })
+                }
+                KeyCode::Backspace => {
+                    name.pop();
+                    Some(Dialog::SaveFilter {
+                        filter,
+                        name,
+                        replaces: None,
+                    })
+                }
+                KeyCode::Enter if !name.is_empty() => match self.parse_filter(&filter) {
+                    // saving would replace the filters in the file that couldn't be read
+                    Ok(_) if self.saved_filters_error.is_some() => {
+                        self.status_message = self.saved_filters_error.clone();
+                        Some(Dialog::Filter {
+                            input: filter,
+                            error: None,
+                        })
+                    }
+                    Ok(parsed) => {
+                        let overwrite = replaces.is_some();
+                        match config::add_saved_filter(
+                            &mut self.saved_filters,
+                            &name,
+                            filter.clone(),
+                            overwrite,
+                        ) {
+                            Ok(()) => {
+                                self.status_message =
+                                    Some(match config::save_filters(&self.saved_filters) {
+                                        Ok(()) => format!("Saved filter @{}", name),
+                                        Err(e) => format!("Failed to save filter @{}: {}", name, e),
+                                    });
+                                self.set_filter(parsed);
+                                None
+                            }
+                            Err(existing) => Some(Dialog::SaveFilter {
+                                filter,
+                                name,
+                                replaces: Some(existing),
+                            }),
+                        }
+                    }
+                    Err(e) => Some(Dialog::Filter {
+                        input: filter,
+                        error: Some(e),
+                    }),
+                },
+                KeyCode::Esc => Some(Dialog::Filter {
+                    input: filter,
+                    error: None,
+                }),
+                _ => Some(Dialog::SaveFilter {
+                    filter,
+                    name,
+                    replaces,
+                }),
+            },
+        };
+    }
+
+    /// Parses a filter expression; an empty expression clears the filter.
+    fn parse_filter(&self, input: &str) -> Result<Option<Filter>, String> {
+        if input.trim().is_empty() {
+            return Ok(None);
+        }
+        Filter::parse(input, &self.saved_filters)
+            .map(Some)
+            .map_err(|e| e.to_string())
+    }
+
+    fn set_filter(&mut self, filter: Option<Filter>) {
+        self.filter = filter;
+        self.update_display_jobs();
+        self.job_list_state.select(Some(0));
+        self.update_job_list_scrollbar();
+    }
+
+    fn ui(&mut self, f: &mut Frame) {
+        // Store terminal width for resize calculations
+        self.terminal_width = f.area().width;
+        
+        // Layout
+
+        let content_help = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(3), Constraint::Length(1)].as_ref())
+            .split(f.area());
+
+        let master_detail = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(self.split_ratio), Constraint::Percentage(100 - self.split_ratio)].as_ref())
+            .split(content_help[0]);
+
+        // Split the job list area to make room for scrollbar
+        let job_area_with_scrollbar = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Length(1), Constraint::Min(0)].as_ref())
+            .split(master_detail[0]);
+
+        let job_detail_log = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints(
+                [
+                    Constraint::Length(if self.log_progress.is_some() { 8 } else { 7 }),
+                    Constraint::Min(3),
+                ]
+                .as_ref(),
+            )
+            .split(master_detail[1]);
+
+        // Help
+        let help_options = if self.log_search_active {
+            vec![("enter", "search"), ("esc", "cancel")]
+        } else if self.focus == Focus::Log {
+            vec![
+                ("q", "quit"),
+                ("h", "back to jobs"),
+                ("⏶/⏷", "scroll"),
+                ("/", "search log"),
+                ("n/N", "next/prev match"),
+                ("e/E", "next/prev error"),
+                ("t", "follow/pause"),
+                ("G", "tail"),
+                ("v/V", "pager/editor"),
+                ("a", "attach shell"),
+                ("y/Y", "copy/save"),
+                ("o", "toggle stdout/stderr"),
+                ("s", "log layout"),
+                ("w", "toggle text wrap"),
+                ("x", "text/escaped/hex"),
+            ]
+        } else if self.fuzzy_finder_active {
+            vec![
+                ("tab", self.fuzzy_finder_mode.label()),
+                ("⏶/⏷", "navigate"),
+                ("enter", "keep matches"),
+                ("esc", "clear"),
+                ("ctrl+e", "expand"),
+                ("ctrl+x", "cancel all matches"),
+            ]
+        } else {
+            match &self.view_mode {
+                ViewMode::AllJobs => vec![
+                    ("q", "quit"),
+                    ("/", "fuzzy find"),
+                    ("⏶/⏷", "navigate"),
+                    ("l", "focus log"),
+                    ("e", "next error"),
+                    ("enter", "expand array/group"),
+                    ("g", "group by"),
+                    ("f/F", "filter/clear"),
+                    ("c", "cancel job"),
+                    ("a", "attach shell"),
+                    ("b/r", "submit/resubmit"),
+                    ("y/Y", "copy/save"),
+                    ("X", "export jobs"),
+                    ("p/P", "pin/tiles"),
+                    ("o", "toggle stdout/stderr"),
+                    ("s", "log layout"),
+                    ("w", "toggle text wrap"),
+                ],
+                ViewMode::ArrayJobDetails(_) => vec![
+                    ("q", "quit"),
+                    ("/", "fuzzy find"),
+                    ("⏶/⏷", "navigate"),
+                    ("esc", "back to jobs"),
+                    ("f/F", "filter/clear"),
+                    ("c", "cancel job"),
+                    ("a", "attach shell"),
+                    ("b/r", "submit/resubmit"),
+                    ("y/Y", "copy/save"),
+                    ("X", "export jobs"),
+                    ("p/P", "pin/tiles"),
+                    ("o", "toggle stdout/stderr"),
+                    ("s", "log layout"),
+                    ("w", "toggle text wrap"),
+                ],
+            }
+        };
+        let blue_style = Style::default().fg(Color::Blue);
+        let light_blue_style = Style::default().fg(Color::LightBlue);
+
+        let help = match &self.status_message {
+            Some(message) => Line::styled(message.as_str(), Style::default().fg(Color::Yellow)),
+            None if self.log_search_active => {
+                let mut spans = vec![
+                    Span::styled("log /", blue_style),
+                    Span::raw(self.log_search_input.as_str()),
+                    Span::styled("█", Style::default().add_modifier(Modifier::SLOW_BLINK)),
+                    Span::raw("  "),
+                ];
+                match &self.log_search_error {
+                    Some(e) => spans.push(Span::styled(
+                        e.lines().last().unwrap_or_default(),
+                        Style::default().fg(Color::Red),
+                    )),
+                    None => spans.push(Span::styled(
+                        format!("{} matching lines", self.log_search_matches.len()),
+                        light_blue_style,
+                    )),
+                }
+                Line::from(spans)
+            }
+            None if self.fuzzy_finder_active => {
+                let mut spans = vec![
+                    Span::styled("/", blue_style),
+                    Span::raw(self.fuzzy_finder_input.as_str()),
+                    Span::styled("█", Style::default().add_modifier(Modifier::SLOW_BLINK)),
+                    Span::raw("  "),
+                ];
+                match &self.fuzzy_finder_error {
+                    Some(e) => spans.push(Span::styled(
+                        e.lines().last().unwrap_or_default(),
+                        Style::default().fg(Color::Red),
+                    )),
+                    None => {
+                        for (key, description) in &help_options {
+                            spans.push(Span::styled(*key, blue_style));
+                            spans.push(Span::raw(": "));
+                            spans.push(Span::styled(*description, light_blue_style));
+                            spans.push(Span::raw(" | "));
+                        }
+                        spans.pop();
+                    }
+                }
+                Line::from(spans)
+            }
+            None => Line::from(help_options.iter().fold(
+                Vec::new(),
+                |mut acc, (key, description)| {
+                    if !acc.is_empty() {
+                        acc.push(Span::raw(" | "));
+                    }
+                    acc.push(Span::styled(*key, blue_style));
+                    acc.push(Span::raw(": "));
+                    acc.push(Span::styled(*description, light_blue_style));
+                    acc
+                },
+            )),
+        };
 
         let help = Paragraph::new(help);
         f.render_widget(help, content_help[1]);
@@ -506,30 +1831,61 @@ impl App {
             .display_jobs
             .iter()
             .map(|j| {
-                let id_display = if j.is_array && j.task_count.is_some() {
-                    format!("{} [{}]", j.array_id, j.task_count.unwrap())
-                } else {
-                    j.id()
-                };
-                
+                if let Some(group) = &j.group {
+                    return group_row(group, j.depth);
+                }
+
+                let h = &j.highlights;
+
                 // Create colored cells for each column
                 let cells = vec![
                     // ST - white
-                    Cell::from(Span::styled(format!("{}", &j.state_compact), Style::default().fg(Color::White))),
+                    Cell::from(highlighted(
+                        &j.state_compact,
+                        h.get(Column::State),
+                        Style::default().fg(Color::White),
+                    )),
                     // Job ID - yellow (or cyan for array jobs)
-                    Cell::from(Span::styled(format!("{}", id_display), if j.is_array {
-                        Style::default().fg(Color::Cyan)
-                    } else {
-                        Style::default().fg(Color::Yellow)
-                    })),
+                    Cell::from(highlighted(
+                        &j.display_id(),
+                        h.get(Column::Id),
+                        if j.is_array {
+                            Style::default().fg(Color::Cyan)
+                        } else {
+                            Style::default().fg(Color::Yellow)
+                        },
+                    )),
                     // Partition - blue
-                    Cell::from(Span::styled(format!("{}", &j.partition), Style::default().fg(Color::Blue))),
+                    Cell::from(highlighted(
+                        &j.partition,
+                        h.get(Column::Partition),
+                        Style::default().fg(Color::Blue),
+                    )),
                     // User - green
-                    Cell::from(Span::styled(format!("{}", &j.user), Style::default().fg(Color::Green))),
+                    Cell::from(highlighted(
+                        &j.user,
+                        h.get(Column::User),
+                        Style::default().fg(Color::Green),
+                    )),
                     // Time - red
-                    Cell::from(Span::styled(format!("{}", &j.time), Style::default().fg(Color::Red))),
-                    // Name - white
-                    Cell::from(Span::styled(format!("{}", &j.name), Style::default().fg(Color::White))),
+                    Cell::from(Span::styled(
+                        j.time.as_str(),
+                        Style::default().fg(Color::Red),
+                    )),
+                    // Name - white, indented below its group
+                    Cell::from({
+                        let mut name = highlighted(
+                            &j.name,
+                            h.get(Column::Name),
+                            Style::default().fg(Color::White),
+                        );
+                        if self.pinned_jobs.iter().any(|p| p.id == j.id()) {
+                            name.spans
+                                .insert(0, Span::styled("● ", Style::default().fg(Color::Magenta)));
+                        }
+                        name.spans.insert(0, Span::raw("  ".repeat(j.depth)));
+                        name
+                    }),
                 ];
                 
                 Row::new(cells)
@@ -537,17 +1893,41 @@ impl App {
             .collect();
 
         let title = match &self.view_mode {
+            ViewMode::AllJobs if !self.group_by.is_empty() => format!(
+                "Jobs ({}) - grouped by {}",
+                self.display_jobs
+                    .iter()
+                    .filter(|j| j.group.is_none())
+                    .count(),
+                self.group_by.iter().map(|k| k.label()).join(" > "),
+            ),
             ViewMode::AllJobs => format!("Jobs ({}) - Cyan = Array Jobs", self.display_jobs.len()),
-            ViewMode::ArrayJobDetails(array_id) => format!("Array Job {} Tasks ({})", array_id, self.display_jobs.len()),
+            ViewMode::ArrayJobDetails(array_id) => {
+                format!("Array Job {} Tasks ({})", array_id, self.display_jobs.len())
+            }
+        };
+        let title = match &self.filter {
+            Some(filter) => format!("{} [filter: {}]", title, filter.source()),
+            None => title,
+        };
+        let title = if self.fuzzy_finder_input.is_empty() {
+            title
+        } else {
+            format!(
+                "{} [{}: {}]",
+                title,
+                self.fuzzy_finder_mode.label(),
+                self.fuzzy_finder_input
+            )
         };
 
         let constraints = [
-            Constraint::Length(self.column_widths[0]),  // State compact
-            Constraint::Length(self.column_widths[1]),  // Job ID
-            Constraint::Length(self.column_widths[2]),  // Partition
-            Constraint::Length(self.column_widths[3]),  // User
-            Constraint::Length(self.column_widths[4]),  // Time
-            Constraint::Min(self.column_widths[5]),     // Name (expandable)
+            Constraint::Length(self.column_widths[0]), // State compact
+            Constraint::Length(self.column_widths[1]), // Job ID
+            Constraint::Length(self.column_widths[2]), // Partition
+            Constraint::Length(self.column_widths[3]), // User
+            Constraint::Length(self.column_widths[4]), // Time
+            Constraint::Min(self.column_widths[5]),    // Name (expandable)
         ];
         // Create header with bordered appearance using box drawing characters
         let header_style = Style::default().add_modifier(Modifier::BOLD);
@@ -591,6 +1971,7 @@ impl App {
                         match self.focus {
                             Focus::Jobs => Style::default().fg(Color::Green),
                             Focus::FuzzyFinder => Style::default().fg(Color::Yellow),
+                            Focus::Log => Style::default(),
                         }
                     }),
             )
@@ -681,79 +2062,58 @@ impl App {
                 OutputFileView::Stdout => "stdout ",
                 OutputFileView::Stderr => "stderr ",
             };
-            let stdout = Line::from(vec![
+            let (path, path_error, path_source) = match self.output_file_view {
+                OutputFileView::Stdout => (&j.stdout, &j.stdout_error, &j.stdout_source),
+                OutputFileView::Stderr => (&j.stderr, &j.stderr_error, &j.stderr_source),
+            };
+            let mut stdout = Line::from(vec![
                 Span::styled(ui_stdout_text, Style::default().fg(Color::Yellow)),
                 Span::raw(" "),
-                Span::raw(
-                    match self.output_file_view {
-                        OutputFileView::Stdout => &j.stdout,
-                        OutputFileView::Stderr => &j.stderr,
-                    }
-                    .as_ref()
-                    .map(|p| p.to_str().unwrap_or_default())
-                    .unwrap_or_default(),
-                ),
+                match (path, path_error) {
+                    (Some(p), _) => Span::raw(p.to_str().unwrap_or_default()),
+                    (None, Some(e)) => Span::styled(e.as_str(), Style::default().fg(Color::Red)),
+                    (None, None) => Span::raw(""),
+                },
             ]);
+            if let Some(source) = path_source {
+                stdout.push_span(Span::styled(
+                    format!(" (found via {})", source),
+                    Style::default().add_modifier(Modifier::DIM),
+                ));
+            }
 
-            Text::from(vec![state, command, nodes, tres, stdout])
+            let mut lines = vec![state, command, nodes, tres, stdout];
+            if let Some(p) = &self.log_progress {
+                let mut spans = vec![
+                    Span::styled("Progress", Style::default().fg(Color::Yellow)),
+                    Span::raw(" "),
+                    Span::raw(format!("{:.0}%", p.percent)),
+                ];
+                let details = [p.count.as_deref(), p.rate.as_deref()]
+                    .into_iter()
+                    .flatten()
+                    .join(", ");
+                if !details.is_empty() {
+                    spans.push(Span::styled(
+                        format!(" ({})", details),
+                        Style::default().add_modifier(Modifier::DIM),
+                    ));
+                }
+                if let Some(eta) = &p.eta {
+                    spans.push(Span::raw(format!(", ETA {}", eta)));
+                }
+                lines.push(Line::from(spans));
+            }
+            Text::from(lines)
         });
         let job_detail = Paragraph::new(job_detail.unwrap_or_default())
             .block(Block::default().title("Details").borders(Borders::ALL));
         f.render_widget(job_detail, job_detail_log[0]);
 
-        // Log
-        let log_area = job_detail_log[1];
-        let log_title = Line::from(vec![
-            Span::raw(match self.output_file_view {
-                OutputFileView::Stdout => "stdout",
-                OutputFileView::Stderr => "stderr",
-            }),
-            Span::styled(
-                match self.job_output_anchor {
-                    ScrollAnchor::Top if self.job_output_offset == 0 => "[T]".to_string(),
-                    ScrollAnchor::Top => format!("[T+{}]", self.job_output_offset),
-                    ScrollAnchor::Bottom if self.job_output_offset == 0 => "".to_string(),
-                    ScrollAnchor::Bottom => format!("[B-{}]", self.job_output_offset),
-                },
-                Style::default().add_modifier(Modifier::DIM),
-            ),
-        ]);
-        let log_block = Block::default().title(log_title).borders(Borders::ALL);
-
-        // let job_log = self.job_stdout.as_deref().map(|s| {
-        //     string_for_paragraph(
-        //         s,
-        //         log_block.inner(log_area).height as usize,
-        //         log_block.inner(log_area).width as usize,
-        //         self.job_stdout_offset as usize,
-        //     )
-        // }).unwrap_or_else(|e| {
-        //     self.job_stdout_offset = 0;
-        //     "".to_string()
-        // });
-
-        let log = match self.job_output.as_deref() {
-            Ok(s) => Paragraph::new(fit_text(
-                s,
-                log_block.inner(log_area).height as usize,
-                log_block.inner(log_area).width as usize,
-                self.job_output_anchor,
-                self.job_output_offset as usize,
-                self.job_output_wrap,
-            )),
-            Err(e) => Paragraph::new(e.to_string())
-                .style(Style::default().fg(Color::Red))
-                .wrap(Wrap { trim: true }),
-        }
-        .block(log_block);
-
-        // Store log area for mouse interaction
-        self.job_output_area = log_area;
-        f.render_widget(log, log_area);
-
-        // Render fuzzy finder if active
-        if self.fuzzy_finder_active {
-            self.render_fuzzy_finder(f);
+        if self.show_tiles {
+            self.render_tiles(f, job_detail_log[1]);
+        } else {
+            self.render_log(f, job_detail_log[1]);
         }
 
         if let Some(dialog) = &self.dialog {
@@ -774,6 +2134,30 @@ impl App {
                     .split(r)[1]
             }
 
+            /// The lines of a list to pick from, scrolled to keep the selected item visible.
+            fn picker_lines<'a>(
+                items: impl Iterator<Item = String>,
+                selected: usize,
+            ) -> Vec<Line<'a>> {
+                const MAX_LINES: usize = 10;
+                let first = selected.saturating_sub(MAX_LINES - 1);
+                items
+                    .enumerate()
+                    .skip(first)
+                    .take(MAX_LINES)
+                    .map(|(i, item)| {
+                        let style = if i == selected {
+                            Style::default()
+                                .bg(Color::Blue)
+                                .add_modifier(Modifier::BOLD)
+                        } else {
+                            Style::default()
+                        };
+                        Line::styled(item, style)
+                    })
+                    .collect()
+            }
+
             match dialog {
                 Dialog::ConfirmCancelJob(id, is_array) => {
                     let message = if *is_array {
@@ -804,6 +2188,516 @@ impl App {
                     f.render_widget(Clear, area);
                     f.render_widget(dialog, area);
                 }
+                Dialog::ConfirmCancelJobs(ids) => {
+                    let message = Line::from(vec![
+                        Span::raw("Cancel all "),
+                        Span::styled(
+                            ids.len().to_string(),
+                            Style::default().add_modifier(Modifier::BOLD),
+                        ),
+                        Span::raw(" listed jobs ("),
+                        Span::raw(ids.join(", ")),
+                        Span::raw(")?"),
+                    ]);
+
+                    let dialog = Paragraph::new(message)
+                        .style(Style::default().fg(Color::White))
+                        .wrap(Wrap { trim: true })
+                        .block(
+                            Block::default()
+                                .title("Confirm")
+                                .borders(Borders::ALL)
+                                .style(Style::default().fg(Color::Red)),
+                        );
+
+                    let area = centered_lines(75, 4, f.area());
+                    f.render_widget(Clear, area);
+                    f.render_widget(dialog, area);
+                }
+                Dialog::Filter { input, error } => {
+                    let mut lines = vec![Line::from(vec![
+                        Span::raw(input.as_str()),
+                        Span::styled("█", Style::default().add_modifier(Modifier::SLOW_BLINK)),
+                    ])];
+                    lines.push(match error {
+                        Some(e) => Line::styled(e.as_str(), Style::default().fg(Color::Red)),
+                        None => Line::styled(
+                            "e.g. state:R partition:gpu name~^train time>2h @saved",
+                            Style::default().add_modifier(Modifier::DIM),
+                        ),
+                    });
+
+                    let dialog = Paragraph::new(lines)
+                        .style(Style::default().fg(Color::White))
+                        .block(
+                            Block::default()
+                                .title("Filter (enter: apply, ctrl+s: save, esc: cancel)")
+                                .borders(Borders::ALL)
+                                .style(Style::default().fg(Color::Yellow)),
+                        );
+
+                    let area = centered_lines(75, 4, f.area());
+                    f.render_widget(Clear, area);
+                    f.render_widget(dialog, area);
+                }
+                Dialog::Copy { save } => {
+                    let lines: Vec<Line> = CopyItem::ALL
+                        .into_iter()
+                        .map(|item| {
+                            Line::from(vec![
+                                Span::styled(
+                                    item.key().to_string(),
+                                    Style::default().fg(Color::Blue),
+                                ),
+                                Span::raw("  "),
+                                Span::raw(item.label()),
+                            ])
+                        })
+                        .collect();
+                    let height = lines.len() as u16 + 2;
+
+                    let dialog = Paragraph::new(lines)
+                        .style(Style::default().fg(Color::White))
+                        .block(
+                            Block::default()
+                                .title(if *save {
+                                    "Save (esc: cancel)"
+                                } else {
+                                    "Copy to clipboard (esc: cancel)"
+                                })
+                                .borders(Borders::ALL)
+                                .style(Style::default().fg(Color::Yellow)),
+                        );
+
+                    let area = centered_lines(50, height, f.area());
+                    f.render_widget(Clear, area);
+                    f.render_widget(dialog, area);
+                }
+                Dialog::SaveAs { text, path } => {
+                    let lines = vec![
+                        Line::from(vec![
+                            Span::raw(path.as_str()),
+                            Span::styled("█", Style::default().add_modifier(Modifier::SLOW_BLINK)),
+                        ]),
+                        Line::styled(
+                            format!("{} lines", text.lines().count()),
+                            Style::default().add_modifier(Modifier::DIM),
+                        ),
+                    ];
+
+                    let dialog = Paragraph::new(lines)
+                        .style(Style::default().fg(Color::White))
+                        .block(
+                            Block::default()
+                                .title("Save as (enter: save, esc: cancel)")
+                                .borders(Borders::ALL)
+                                .style(Style::default().fg(Color::Yellow)),
+                        );
+
+                    let area = centered_lines(75, 4, f.area());
+                    f.render_widget(Clear, area);
+                    f.render_widget(dialog, area);
+                }
+                Dialog::GroupBy(keys) => {
+                    let mut lines: Vec<Line> = GroupKey::ALL
+                        .into_iter()
+                        .map(|k| {
+                            let level = keys
+                                .iter()
+                                .position(|&used| used == k)
+                                .map(|i| format!("{}.", i + 1));
+                            Line::from(vec![
+                                Span::styled(k.key().to_string(), Style::default().fg(Color::Blue)),
+                                Span::raw("  "),
+                                Span::raw(format!("{:<3}", level.unwrap_or_default())),
+                                Span::raw(k.label()),
+                            ])
+                        })
+                        .collect();
+                    let summary = if keys.is_empty() {
+                        "not grouped".to_string()
+                    } else {
+                        keys.iter().map(|k| k.label()).join(" › ")
+                    };
+                    lines.push(Line::styled(
+                        summary,
+                        Style::default().add_modifier(Modifier::DIM),
+                    ));
+                    let height = lines.len() as u16 + 2;
+
+                    let dialog = Paragraph::new(lines)
+                        .style(Style::default().fg(Color::White))
+                        .block(
+                            Block::default()
+                                .title("Group by, outermost first (enter: apply, esc: cancel)")
+                                .borders(Borders::ALL)
+                                .style(Style::default().fg(Color::Yellow)),
+                        );
+
+                    let area = centered_lines(60, height, f.area());
+                    f.render_widget(Clear, area);
+                    f.render_widget(dialog, area);
+                }
+                Dialog::Export { path, error } => {
+                    let jobs = self.exported_jobs().len();
+                    let info = match (error, JobsFormat::from_path(Path::new(path))) {
+                        (Some(e), _) => Line::styled(e.as_str(), Style::default().fg(Color::Red)),
+                        (None, Some(format)) => Line::styled(
+                            format!(
+                                "{} jobs as {}",
+                                jobs,
+                                format!("{:?}", format).to_uppercase()
+                            ),
+                            Style::default().add_modifier(Modifier::DIM),
+                        ),
+                        (None, None) => Line::styled(
+                            format!("{} jobs, as .json, .csv or .tsv", jobs),
+                            Style::default().add_modifier(Modifier::DIM),
+                        ),
+                    };
+                    let lines = vec![
+                        Line::from(vec![
+                            Span::raw(path.as_str()),
+                            Span::styled("█", Style::default().add_modifier(Modifier::SLOW_BLINK)),
+                        ]),
+                        info,
+                    ];
+
+                    let dialog = Paragraph::new(lines)
+                        .style(Style::default().fg(Color::White))
+                        .block(
+                            Block::default()
+                                .title("Export listed jobs (enter: export, esc: cancel)")
+                                .borders(Borders::ALL)
+                                .style(Style::default().fg(Color::Yellow)),
+                        );
+
+                    let area = centered_lines(75, 4, f.area());
+                    f.render_widget(Clear, area);
+                    f.render_widget(dialog, area);
+                }
+                Dialog::AttachNode {
+                    id,
+                    nodes,
+                    selected,
+                } => {
+                    let lines = picker_lines(nodes.iter().cloned(), *selected);
+                    let height = lines.len() as u16 + 2;
+
+                    let dialog = Paragraph::new(lines)
+                        .style(Style::default().fg(Color::White))
+                        .block(
+                            Block::default()
+                                .title(format!(
+                                    "Attach a shell to {} on (enter: attach, esc: cancel)",
+                                    id
+                                ))
+                                .borders(Borders::ALL)
+                                .style(Style::default().fg(Color::Yellow)),
+                        );
+
+                    let area = centered_lines(50, height, f.area());
+                    f.render_widget(Clear, area);
+                    f.render_widget(dialog, area);
+                }
+                Dialog::Submit(form) => {
+                    let mut lines: Vec<Line> = SubmitField::ALL
+                        .into_iter()
+                        .enumerate()
+                        .map(|(i, field)| {
+                            let mut spans = vec![
+                                Span::styled(
+                                    format!("{:>10} ", field.label()),
+                                    Style::default().fg(Color::Blue),
+                                ),
+                                Span::raw(form.value(field)),
+                            ];
+                            if i == form.focus {
+                                spans.push(Span::styled(
+                                    "█",
+                                    Style::default().add_modifier(Modifier::SLOW_BLINK),
+                                ));
+                            }
+                            Line::from(spans)
+                        })
+                        .collect();
+                    lines.push(match &form.error {
+                        Some(e) => Line::styled(e.as_str(), Style::default().fg(Color::Red)),
+                        None => Line::styled(
+                            "empty fields are left to the script's #SBATCH lines",
+                            Style::default().add_modifier(Modifier::DIM),
+                        ),
+                    });
+                    let height = lines.len() as u16 + 2;
+
+                    let dialog = Paragraph::new(lines)
+                        .style(Style::default().fg(Color::White))
+                        .block(
+                            Block::default()
+                                .title("Submit (tab: next field, ctrl+r: recent scripts, enter: submit, esc: cancel)")
+                                .borders(Borders::ALL)
+                                .style(Style::default().fg(Color::Yellow)),
+                        );
+
+                    let area = centered_lines(75, height, f.area());
+                    f.render_widget(Clear, area);
+                    f.render_widget(dialog, area);
+                }
+                Dialog::RecentScripts {
+                    scripts, selected, ..
+                } => {
+                    let lines =
+                        picker_lines(scripts.iter().map(|s| s.display().to_string()), *selected);
+                    let height = lines.len() as u16 + 2;
+
+                    let dialog = Paragraph::new(lines)
+                        .style(Style::default().fg(Color::White))
+                        .block(
+                            Block::default()
+                                .title("Recent scripts (enter: pick, esc: back)")
+                                .borders(Borders::ALL)
+                                .style(Style::default().fg(Color::Yellow)),
+                        );
+
+                    let area = centered_lines(75, height, f.area());
+                    f.render_widget(Clear, area);
+                    f.render_widget(dialog, area);
+                }
+                Dialog::SaveFilter {
+                    filter,
+                    name,
+                    replaces,
+                } => {
+                    let info = match replaces {
+                        Some(existing) => Line::styled(
+                            format!("@{} is already {}, enter replaces it", name, existing),
+                            Style::default().fg(Color::Red),
+                        ),
+                        None => Line::styled(
+                            filter.as_str(),
+                            Style::default().add_modifier(Modifier::DIM),
+                        ),
+                    };
+                    let lines = vec![
+                        Line::from(vec![
+                            Span::raw("@"),
+                            Span::raw(name.as_str()),
+                            Span::styled("█", Style::default().add_modifier(Modifier::SLOW_BLINK)),
+                        ]),
+                        info,
+                    ];
+
+                    let dialog = Paragraph::new(lines)
+                        .style(Style::default().fg(Color::White))
+                        .block(
+                            Block::default()
+                                .title("Save filter as (enter: save, esc: back)")
+                                .borders(Borders::ALL)
+                                .style(Style::default().fg(Color::Yellow)),
+                        );
+
+                    let area = centered_lines(75, 4, f.area());
+                    f.render_widget(Clear, area);
+                    f.render_widget(dialog, area);
+                }
+            }
+        }
+    }
+
+    /// Renders the log of the selected job, next to its other file in the split layouts.
+    fn render_log(&mut self, f: &mut Frame, area: Rect) {
+        let (stream, other_stream) = match self.output_file_view {
+            OutputFileView::Stdout => ("stdout", "stderr"),
+            OutputFileView::Stderr => ("stderr", "stdout"),
+        };
+        let (log_area, secondary_area) = match (&self.job_output_secondary, self.log_layout) {
+            (Some(_), LogLayout::Stacked | LogLayout::SideBySide) => {
+                let direction = match self.log_layout {
+                    LogLayout::Stacked => Direction::Vertical,
+                    _ => Direction::Horizontal,
+                };
+                let areas = Layout::default()
+                    .direction(direction)
+                    .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+                    .split(area);
+                (areas[0], areas[1])
+            }
+            _ => (area, Rect::default()),
+        };
+        let log_title = Line::from(vec![
+            Span::raw(match self.log_layout {
+                LogLayout::Merged => "stdout+stderr",
+                _ => stream,
+            }),
+            Span::styled(
+                match self.job_output_anchor {
+                    ScrollAnchor::Top if self.job_output_offset == 0 => "[T]".to_string(),
+                    ScrollAnchor::Top => format!("[T+{}]", self.job_output_offset),
+                    ScrollAnchor::Bottom if self.job_output_offset == 0 => "".to_string(),
+                    ScrollAnchor::Bottom => format!("[B-{}]", self.job_output_offset),
+                },
+                Style::default().add_modifier(Modifier::DIM),
+            ),
+            Span::styled(
+                match (self.job_output_follow, self.job_output_new_lines) {
+                    (true, _) => "".to_string(),
+                    (false, 0) => " [paused]".to_string(),
+                    (false, 1) => " [paused, 1 new line]".to_string(),
+                    (false, n) => format!(" [paused, {} new lines]", n),
+                },
+                Style::default().fg(Color::Cyan),
+            ),
+            Span::styled(
+                match (&self.log_search, self.log_search_current) {
+                    (Some(re), Some(i)) => {
+                        format!(" [/{} {}/{}]", re, i + 1, self.log_search_matches.len())
+                    }
+                    (Some(re), None) => format!(" [/{} {}]", re, self.log_search_matches.len()),
+                    (None, _) => "".to_string(),
+                },
+                Style::default().fg(Color::Yellow),
+            ),
+            Span::styled(
+                match self.log_mode {
+                    LogMode::Text => "".to_string(),
+                    mode => format!(" [{}]", mode.label()),
+                },
+                Style::default().add_modifier(Modifier::DIM),
+            ),
+            Span::styled(
+                match self.log_error_count {
+                    0 => "".to_string(),
+                    1 => " [1 error]".to_string(),
+                    n => format!(" [{} errors]", n),
+                },
+                Style::default().fg(Color::Red),
+            ),
+            Span::styled(
+                match self.log_warning_count {
+                    0 => "".to_string(),
+                    1 => " [1 warning]".to_string(),
+                    n => format!(" [{} warnings]", n),
+                },
+                Style::default().fg(Color::Yellow),
+            ),
+        ]);
+        let log_block = Block::default()
+            .title(log_title)
+            .borders(Borders::ALL)
+            .border_style(if self.focus == Focus::Log && self.dialog.is_none() {
+                Style::default().fg(Color::Green)
+            } else {
+                Style::default()
+            });
+
+        // let job_log = self.job_stdout.as_deref().map(|s| {
+        //     string_for_paragraph(
+        //         s,
+        //         log_block.inner(log_area).height as usize,
+        //         log_block.inner(log_area).width as usize,
+        //         self.job_stdout_offset as usize,
+        //     )
+        // }).unwrap_or_else(|e| {
+        //     self.job_stdout_offset = 0;
+        //     "".to_string()
+        // });
+
+        let log = self
+            .log_paragraph(
+                &self.job_output,
+                log_block.inner(log_area),
+                LogDisplay {
+                    mode: self.log_mode,
+                    rules: Some(&self.log_highlighter),
+                    search: self.log_search.as_ref(),
+                    current_line: self
+                        .log_search_current
+                        .map(|i| self.log_search_matches[i])
+                        .or(self.log_problem_current.map(|i| self.log_problems[i].0)),
+                },
+            )
+            .block(log_block);
+
+        // Store log area for mouse interaction
+        self.job_output_area = log_area;
+        self.job_output_secondary_area = secondary_area;
+        f.render_widget(log, log_area);
+
+        if let Some(secondary) = &self.job_output_secondary {
+            if secondary_area.area() > 0 {
+                let block = Block::default().title(other_stream).borders(Borders::ALL);
+                let log = self
+                    .log_paragraph(
+                        secondary,
+                        block.inner(secondary_area),
+                        LogDisplay {
+                            mode: self.log_mode,
+                            rules: Some(&self.log_highlighter),
+                            search: self.log_search.as_ref(),
+                            current_line: None,
+                        },
+                    )
+                    .block(block);
+                f.render_widget(log, secondary_area);
+            }
+        }
+    }
+
+    /// Renders the logs of the pinned jobs in a grid, showing their tails.
+    fn render_tiles(&self, f: &mut Frame, area: Rect) {
+        let n = self.pinned_jobs.len();
+        let cols = (1..=n).find(|c| c * c >= n).unwrap_or(1);
+        let rows = n.div_ceil(cols);
+        let row_areas = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints(vec![Constraint::Ratio(1, rows as u32); rows])
+            .split(area);
+        let selected_id = self
+            .job_list_state
+            .selected()
+            .and_then(|i| self.display_jobs.get(i))
+            .map(|j| j.id());
+        for (row, chunk) in row_areas.iter().zip(
+            self.pinned_jobs
+                .iter()
+                .zip(&self.tile_outputs)
+                .collect_vec()
+                .chunks(cols),
+        ) {
+            let tile_areas = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints(vec![Constraint::Ratio(1, chunk.len() as u32); chunk.len()])
+                .split(*row);
+            for (tile_area, (job, output)) in tile_areas.iter().zip(chunk) {
+                let block = Block::default()
+                    .title(format!("{} {}", job.id, job.name))
+                    .borders(Borders::ALL)
+                    .border_style(if selected_id.as_ref() == Some(&job.id) {
+                        Style::default().fg(Color::Green)
+                    } else {
+                        Style::default()
+                    });
+                let inner = block.inner(*tile_area);
+                let tile = match output {
+                    Ok(log) => Paragraph::new(fit_text(
+                        &log.read().unwrap(),
+                        inner.height as usize,
+                        inner.width as usize,
+                        ScrollAnchor::Bottom,
+                        0,
+                        self.job_output_wrap,
+                        LogDisplay {
+                            mode: self.log_mode,
+                            rules: Some(&self.log_highlighter),
+                            search: None,
+                            current_line: None,
+                        },
+                    )),
+                    Err(e) => Paragraph::new(e.to_string())
+                        .style(Style::default().fg(Color::Red))
+                        .wrap(Wrap { trim: true }),
+                };
+                f.render_widget(tile.block(block), *tile_area);
             }
         }
     }
@@ -812,330 +2706,108 @@ impl App {
     fn activate_fuzzy_finder(&mut self) {
         self.fuzzy_finder_active = true;
         self.focus = Focus::FuzzyFinder;
-        self.fuzzy_finder_input.clear();
-        self.fuzzy_finder_selected = 0;
         self.update_fuzzy_finder();
     }
 
     fn deactivate_fuzzy_finder(&mut self) {
         self.fuzzy_finder_active = false;
         self.focus = Focus::Jobs;
-        self.fuzzy_finder_input.clear();
-        self.fuzzy_finder_filtered_jobs.clear();
-    }
-
-    fn fuzzy_finder_input(&mut self, c: char) {
-        self.fuzzy_finder_input.push(c);
-        self.fuzzy_finder_selected = 0;
-        self.update_fuzzy_finder();
     }
 
-    fn fuzzy_finder_backspace(&mut self) {
-        self.fuzzy_finder_input.pop();
-        self.fuzzy_finder_selected = 0;
+    fn clear_fuzzy_finder(&mut self) {
+        self.deactivate_fuzzy_finder();
+        self.fuzzy_finder_input.clear();
         self.update_fuzzy_finder();
     }
 
-    fn fuzzy_finder_next(&mut self) {
-        if !self.fuzzy_finder_filtered_jobs.is_empty() {
-            self.fuzzy_finder_selected = (self.fuzzy_finder_selected + 1).min(self.fuzzy_finder_filtered_jobs.len() - 1);
-        }
-    }
-
-    fn fuzzy_finder_previous(&mut self) {
-        if self.fuzzy_finder_selected > 0 {
-            self.fuzzy_finder_selected -= 1;
-        }
-    }
-
-    fn fuzzy_finder_select(&mut self) {
-        if let Some(&(original_index, _)) = self.fuzzy_finder_filtered_jobs.get(self.fuzzy_finder_selected) {
-            self.job_list_state.select(Some(original_index));
-            self.update_job_list_scrollbar();
-            self.deactivate_fuzzy_finder();
-        }
+    fn update_fuzzy_finder(&mut self) {
+        self.update_display_jobs();
+        self.job_list_state.select(Some(0));
+        self.update_job_list_scrollbar();
     }
 
-    fn update_fuzzy_finder(&mut self) {
-        let matcher = SkimMatcherV2::default();
-        
+    /// The matcher for the fuzzy finder input, if there is one. An invalid query is shown as the
+    /// fuzzy finder's error and doesn't narrow the list.
+    fn fuzzy_matcher(&mut self) -> Option<Matcher> {
+        self.fuzzy_finder_error = None;
         if self.fuzzy_finder_input.is_empty() {
-            self.fuzzy_finder_filtered_jobs = self.display_jobs
-                .iter()
-                .enumerate()
-                .map(|(i, _)| (i, 0))
-                .collect();
-        } else {
-            let mut scored_jobs = Vec::new();
-            
-            for (i, job) in self.display_jobs.iter().enumerate() {
-                // Search in multiple fields: job_id, name, user, partition, state
-                let searchable_text = format!("{} {} {} {} {}", 
-                    job.id(), job.name, job.user, job.partition, job.state);
-                
-                if let Some(score) = matcher.fuzzy_match(&searchable_text, &self.fuzzy_finder_input) {
-                    scored_jobs.push((i, score));
-                }
-            }
-            
-            // Sort by score (higher is better)
-            scored_jobs.sort_by(|a, b| b.1.cmp(&a.1));
-            self.fuzzy_finder_filtered_jobs = scored_jobs;
-        }
-        
-        // Ensure selected index is valid
-        if self.fuzzy_finder_selected >= self.fuzzy_finder_filtered_jobs.len() {
-            self.fuzzy_finder_selected = 0;
+            return None;
         }
+        Matcher::new(&self.fuzzy_finder_input, self.fuzzy_finder_mode)
+            .map_err(|e| self.fuzzy_finder_error = Some(e))
+            .ok()
     }
 
-    fn render_fuzzy_finder(&self, f: &mut Frame) {
-        // Create an overlay in the center of the screen
-        let area = f.area();
-        let finder_width = (area.width * 3 / 4).max(60).min(area.width.saturating_sub(4));
-        let finder_height = 12.min(area.height.saturating_sub(4));
-        
-        let finder_area = Rect::new(
-            (area.width.saturating_sub(finder_width)) / 2,
-            (area.height.saturating_sub(finder_height)) / 2,
-            finder_width,
-            finder_height,
-        );
-
-        // Clear the background
-        f.render_widget(Clear, finder_area);
-
-        // Split into input and results areas
-        let vertical_layout = Layout::default()
-            .direction(Direction::Vertical)
-            .constraints([
-                Constraint::Length(3), // Input area with border
-                Constraint::Min(0),    // Results area
-            ])
-            .split(finder_area);
-
-        // Input field
-        let input_text = format!("{}", self.fuzzy_finder_input);
-        let input_paragraph = Paragraph::new(input_text)
-            .style(Style::default().fg(Color::Yellow))
-            .block(
-                Block::default()
-                    .borders(Borders::ALL)
-                    .title("Fuzzy Find")
-                    .border_style(Style::default().fg(Color::Yellow))
-            );
-        f.render_widget(input_paragraph, vertical_layout[0]);
-
-        // Results list
-        if !self.fuzzy_finder_filtered_jobs.is_empty() {
-            let visible_height = vertical_layout[1].height.saturating_sub(2) as usize; // -2 for borders
-            
-            // Calculate scroll offset to keep selected item visible
-            let scroll_offset = if self.fuzzy_finder_selected < visible_height {
-                0
-            } else {
-                self.fuzzy_finder_selected.saturating_sub(visible_height - 1)
-            };
-            
-            let results: Vec<Row> = self.fuzzy_finder_filtered_jobs
-                .iter()
-                .skip(scroll_offset)
-                .take(visible_height)
-                .enumerate()
-                .map(|(display_i, &(original_index, _))| {
-                    let actual_i = display_i + scroll_offset;
-                    let job = &self.display_jobs[original_index];
-                    let id_display = if job.is_array && job.task_count.is_some() {
-                        format!("{} [{}]", job.array_id, job.task_count.unwrap())
-                    } else {
-                        job.id()
-                    };
-                    
-                    let style = if actual_i == self.fuzzy_finder_selected {
-                        Style::default().bg(Color::Blue).fg(Color::White)
-                    } else {
-                        Style::default().fg(Color::White)
-                    };
-                    
-                    Row::new(vec![
-                        Cell::from(Span::styled(&job.state_compact, style)),
-                        Cell::from(Span::styled(id_display, style)),
-                        Cell::from(Span::styled(&job.user, style)),
-                        Cell::from(Span::styled(&job.name, style)),
-                    ])
-                })
-                .collect();
-
-            let results_table = Table::new(
-                results,
-                [
-                    Constraint::Length(3),  // State
-                    Constraint::Length(15), // Job ID
-                    Constraint::Length(10), // User
-                    Constraint::Min(0),     // Name
-                ],
-            )
-            .block(
-                Block::default()
-                    .borders(Borders::ALL)
-                    .title(format!("Results ({}/{})", 
-                        self.fuzzy_finder_selected + 1, 
-                        self.fuzzy_finder_filtered_jobs.len()))
-                    .border_style(Style::default().fg(Color::Green))
-            )
-            .column_spacing(1);
-
-            f.render_widget(results_table, vertical_layout[1]);
-        } else {
-            // Show "No results" message
-            let no_results = Paragraph::new("No results found")
-                .style(Style::default().fg(Color::Red))
-                .block(
-                    Block::default()
-                        .borders(Borders::ALL)
-                        .title("Results")
-                        .border_style(Style::default().fg(Color::Red))
-                )
-                .alignment(Alignment::Center);
-            f.render_widget(no_results, vertical_layout[1]);
+    fn confirm_cancel_listed_jobs(&mut self) {
+        if self.fuzzy_finder_input.is_empty() && self.filter.is_none() {
+            self.status_message =
+                Some("Filter or search the job list first to cancel all listed jobs".to_owned());
+            return;
+        }
+        // the tasks of an array row one by one, since its other tasks may not match
+        let ids: Vec<String> = export::row_jobs(&self.display_jobs, &self.narrowed_jobs())
+            .iter()
+            .map(|j| j.id())
+            .collect();
+        if !ids.is_empty() {
+            self.dialog = Some(Dialog::ConfirmCancelJobs(ids));
         }
     }
 }
 
-fn chunked_string(s: &str, first_chunk_size: usize, chunk_size: usize) -> Vec<&str> {
-    let stepped_indices = s
-        .char_indices()
-        .map(|(i, _)| i)
+/// Renders `text` with the chars at `indices` highlighted as fuzzy finder matches.
+fn highlighted(text: &str, indices: &[usize], style: Style) -> Line<'static> {
+    if indices.is_empty() {
+        return Line::from(Span::styled(text.to_owned(), style));
+    }
+    let match_style = style.fg(Color::Black).bg(Color::Yellow);
+    let spans = text
+        .chars()
         .enumerate()
-        .filter(|&(i, _)| {
-            if i > (first_chunk_size) {
-                chunk_size > 0 && (i - first_chunk_size) % chunk_size == 0
-            } else {
-                i == 0 || i == first_chunk_size
-            }
+        .chunk_by(|(i, _)| indices.contains(i))
+        .into_iter()
+        .map(|(is_match, chars)| {
+            let s: String = chars.map(|(_, c)| c).collect();
+            Span::styled(s, if is_match { match_style } else { style })
         })
-        .map(|(_, e)| e)
         .collect::<Vec<_>>();
-    let windows = stepped_indices.windows(2).collect::<Vec<_>>();
-
-    let iter = windows.iter().map(|w| &s[w[0]..w[1]]);
-    let last_index = *stepped_indices.last().unwrap_or(&0);
-    iter.chain(once(&s[last_index..])).collect()
+    Line::from(spans)
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    #[test]
-    fn test_chunked_string() {
-        // Divisible
-        let input = "abcdefghij";
-        let expected = vec!["abcd", "ef", "gh", "ij"];
-        assert_eq!(chunked_string(input, 4, 2), expected);
-
-        // Not divisible
-        let input = "123456789";
-        let expected = vec!["1234", "56", "78", "9"];
-        assert_eq!(chunked_string(input, 4, 2), expected);
-
-        // Smaller
-        let input = "abc";
-        let expected = vec!["abc"];
-        assert_eq!(chunked_string(input, 4, 2), expected);
-
-        // Smaller
-        let input = "abcde";
-        let expected = vec!["abcd", "e"];
-        assert_eq!(chunked_string(input, 4, 2), expected);
-
-        // Empty
-        let input = "";
-        let expected: Vec<&str> = vec![""];
-        assert_eq!(chunked_string(input, 4, 2), expected);
-
-        let input = "123456789";
-        let expected = vec!["1234", "56789"];
-        assert_eq!(chunked_string(input, 4, 0), expected);
-
-        let input = "123456789";
-        let expected = vec!["12", "34", "56", "78", "9"];
-        assert_eq!(chunked_string(input, 0, 2), expected);
-
-        let input = "123456789";
-        let expected = vec!["123456789"];
-        assert_eq!(chunked_string(input, 0, 0), expected);
-    }
+fn group_row(group: &GroupInfo, depth: usize) -> Row<'static> {
+    let style = Style::default()
+        .fg(Color::Magenta)
+        .add_modifier(Modifier::BOLD);
+    let states = group
+        .states
+        .iter()
+        .map(|(state, count)| format!("{count} {state}"))
+        .join(", ");
+    Row::new(vec![
+        Cell::from(Span::styled(if group.collapsed { "▸" } else { "▾" }, style)),
+        Cell::from(Span::styled(format!("[{}]", group.job_count), style)),
+        Cell::from(Span::styled(group.key.label(), style)),
+        Cell::from(""),
+        Cell::from(""),
+        Cell::from(Line::from(vec![
+            Span::styled(format!("{}{}", "  ".repeat(depth), group.value), style),
+            Span::styled(
+                format!("  {} | {}", states, group.resources),
+                Style::default().add_modifier(Modifier::DIM),
+            ),
+        ])),
+    ])
 }
 
-fn fit_text(
-    s: &str,
-    lines: usize,
-    cols: usize,
-    anchor: ScrollAnchor,
-    offset: usize,
-    wrap: bool,
-) -> Text {
-    let s = s.rsplit_once(&['\r', '\n']).map_or(s, |(p, _)| p); // skip everything after last line delimiter
-    let l = s.lines().flat_map(|l| l.split('\r')); // bandaid for term escape codes
-    let iter = match anchor {
-        ScrollAnchor::Top => Either::Left(l),
-        ScrollAnchor::Bottom => Either::Right(l.rev()),
-    };
-    let iter = iter
-        .skip(offset)
-        .flat_map(|l| {
-            let iter = if wrap {
-                Either::Left(
-                    chunked_string(l, cols, cols.saturating_sub(2))
-                        .into_iter()
-                        .enumerate()
-                        .map(|(i, l)| {
-                            if i == 0 {
-                                Line::raw(l.chars().take(cols).collect::<String>())
-                            } else {
-                                Line::default().spans(vec![
-                                    Span::styled(
-                                        "↪ ",
-                                        Style::default().add_modifier(Modifier::DIM),
-                                    ),
-                                    Span::raw(
-                                        l.chars().take(cols.saturating_sub(2)).collect::<String>(),
-                                    ),
-                                ])
-                            }
-                        }),
-                )
-            } else {
-                match l.chars().nth(cols) {
-                    Some(_) => {
-                        // has more chars than cols
-                        Either::Right(once(Line::default().spans(vec![
-                            Span::raw(l.chars().take(cols.saturating_sub(1)).collect::<String>()),
-                            Span::styled("…", Style::default().add_modifier(Modifier::DIM)),
-                        ])))
-                    }
-                    None => {
-                        Either::Right(once(Line::raw(l.chars().take(cols).collect::<String>())))
-                    }
-                }
-            };
-            match anchor {
-                ScrollAnchor::Top => Either::Left(iter),
-                ScrollAnchor::Bottom => Either::Right(iter.rev()),
-            }
-        })
-        .take(lines);
-
-    match anchor {
-        ScrollAnchor::Top => Text::from(iter.collect::<Vec<_>>()),
-        ScrollAnchor::Bottom => Text::from(
-            iter.collect::<Vec<_>>()
-                .into_iter()
-                .rev()
-                .collect::<Vec<_>>(),
-        ),
-    }
+fn scancel(ids: &[String]) {
+    let mut child = Command::new("scancel")
+        .args(ids)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .expect("failed to execute scancel");
+    // reap the process in the background so it doesn't linger as a zombie
+    thread::spawn(move || child.wait());
 }
 
 impl App {
@@ -1196,14 +2868,44 @@ impl App {
 
     fn is_mouse_in_job_list(&self, column: u16, row: u16) -> bool {
         let area = &self.job_list_area;
-        column >= area.x && column < area.x + area.width &&
-        row >= area.y && row < area.y + area.height
+        column >= area.x
+            && column < area.x + area.width
+            && row >= area.y
+            && row < area.y + area.height
     }
 
     fn is_mouse_in_job_output(&self, column: u16, row: u16) -> bool {
-        let area = &self.job_output_area;
-        column >= area.x && column < area.x + area.width &&
-        row >= area.y && row < area.y + area.height
+        [self.job_output_area, self.job_output_secondary_area]
+            .iter()
+            .any(|area| {
+                column >= area.x
+                    && column < area.x + area.width
+                    && row >= area.y
+                    && row < area.y + area.height
+            })
+    }
+
+    /// A log, or why it can't be read, fit into `area` with the current scroll position.
+    fn log_paragraph(
+        &self,
+        log: &Result<SharedLogBuffer, FileWatcherError>,
+        area: Rect,
+        display: LogDisplay,
+    ) -> Paragraph<'static> {
+        match log {
+            Ok(log) => Paragraph::new(fit_text(
+                &log.read().unwrap(),
+                area.height as usize,
+                area.width as usize,
+                self.job_output_anchor,
+                self.job_output_offset,
+                self.job_output_wrap,
+                display,
+            )),
+            Err(e) => Paragraph::new(e.to_string())
+                .style(Style::default().fg(Color::Red))
+                .wrap(Wrap { trim: true }),
+        }
     }
 
     fn is_mouse_in_resize_area(&self, column: u16, row: u16) -> bool {
@@ -1304,16 +3006,219 @@ impl App {
 
     fn focus_next_panel(&mut self) {
         match self.focus {
-            Focus::Jobs => self.focus = Focus::Jobs,
-            Focus::FuzzyFinder => {},
+            Focus::Jobs => self.focus = Focus::Log,
+            Focus::FuzzyFinder | Focus::Log => {}
         }
     }
 
     fn focus_previous_panel(&mut self) {
         match self.focus {
-            Focus::Jobs => self.focus = Focus::Jobs,
-            Focus::FuzzyFinder => {},
+            Focus::Log => self.focus = Focus::Jobs,
+            Focus::Jobs | Focus::FuzzyFinder => {}
+        }
+    }
+
+    /// Scrolls the log by `delta` lines, positive is down.
+    fn scroll_job_output_by(&mut self, delta: isize) {
+        let down = match self.job_output_anchor {
+            ScrollAnchor::Top => delta > 0,
+            ScrollAnchor::Bottom => delta < 0,
+        };
+        self.job_output_offset = if down {
+            self.job_output_offset.saturating_add(delta.unsigned_abs())
+        } else {
+            self.job_output_offset.saturating_sub(delta.unsigned_abs())
+        };
+    }
+
+    fn update_log_search(&mut self) {
+        self.log_search_error = None;
+        self.log_search = None;
+        if !self.log_search_input.is_empty() {
+            // smart case, like the fuzzy finder
+            let case_sensitive = self.log_search_input.chars().any(|c| c.is_uppercase());
+            match RegexBuilder::new(&self.log_search_input)
+                .case_insensitive(!case_sensitive)
+                .build()
+            {
+                Ok(re) => self.log_search = Some(re),
+                Err(e) => self.log_search_error = Some(e.to_string()),
+            }
+        }
+        self.log_search_current = None;
+        self.log_search_matches.clear();
+        self.update_log_search_matches(0);
+    }
+
+    /// Updates what's derived from the log after the lines from `from` on were added or changed.
+    fn update_log_caches(&mut self, from: usize) {
+        if let Ok(log) = &self.job_output {
+            let log = log.read().unwrap();
+            let first_line = log.first_line();
+            // keep the view in place when old lines are dropped
+            if matches!(self.job_output_anchor, ScrollAnchor::Top) {
+                self.job_output_offset = self
+                    .job_output_offset
+                    .saturating_sub(first_line.saturating_sub(self.job_output_first_line));
+            }
+            self.job_output_first_line = first_line;
+
+            // a newly opened log has no new lines
+            if from > 0 && !self.job_output_follow {
+                self.job_output_new_lines += log.end().saturating_sub(self.job_output_end);
+            }
+            self.job_output_end = log.end();
+        }
+        self.update_log_search_matches(from);
+        self.update_log_problems(from);
+        self.log_progress = match &self.job_output {
+            Ok(log) => Progress::find(&log.read().unwrap()),
+            Err(_) => None,
+        };
+    }
+
+    fn update_log_search_matches(&mut self, from: usize) {
+        let current_line = self.log_search_current.map(|i| self.log_search_matches[i]);
+        let (Some(re), Ok(log)) = (&self.log_search, &self.job_output) else {
+            self.log_search_matches.clear();
+            self.log_search_current = None;
+            return;
+        };
+        let log = log.read().unwrap();
+        let lines = log.first_line()..from;
+        self.log_search_matches.retain(|l| lines.contains(l));
+        self.log_search_matches.extend(
+            log.lines_from(from)
+                .filter(|(_, l)| re.is_match(&ansi::strip(l)))
+                .map(|(i, _)| i),
+        );
+        self.log_search_current =
+            current_line.and_then(|line| self.log_search_matches.iter().position(|&l| l == line));
+    }
+
+    fn update_log_problems(&mut self, from: usize) {
+        let current_line = self.log_problem_current.map(|i| self.log_problems[i].0);
+        if let Ok(log) = &self.job_output {
+            let log = log.read().unwrap();
+            let lines = log.first_line()..from;
+            self.log_problems.retain(|(l, _)| lines.contains(l));
+            self.log_problems
+                .extend(log.lines_from(from).filter_map(|(i, l)| {
+                    self.log_highlighter
+                        .classify(&ansi::strip(l))
+                        .map(|(level, _)| (i, level))
+                }));
+        } else {
+            self.log_problems.clear();
+        }
+        self.log_error_count = self
+            .log_problems
+            .iter()
+            .filter(|(_, level)| *level == Level::Error)
+            .count();
+        self.log_warning_count = self.log_problems.len() - self.log_error_count;
+        self.log_problem_current =
+            current_line.and_then(|line| self.log_problems.iter().position(|&(l, _)| l == line));
+    }
+
+    /// Moves the log view to the next (or previous) matching line, wrapping around at the end.
+    fn log_search_next(&mut self, forward: bool) {
+        self.log_search_current =
+            self.next_log_line(&self.log_search_matches, self.log_search_current, forward);
+        if let Some(i) = self.log_search_current {
+            self.log_problem_current = None;
+            self.scroll_to_log_line(self.log_search_matches[i]);
+        }
+    }
+
+    /// Moves the log view to the next (or previous) error or warning line.
+    fn log_problem_next(&mut self, forward: bool) {
+        let lines: Vec<usize> = self.log_problems.iter().map(|(l, _)| *l).collect();
+        self.log_problem_current = self.next_log_line(&lines, self.log_problem_current, forward);
+        if let Some(i) = self.log_problem_current {
+            self.log_search_current = None;
+            self.scroll_to_log_line(lines[i]);
+        }
+    }
+
+    /// The index into `lines` (sorted log line indices) to jump to from `current`. Without a current
+    /// line, starts from the first line currently visible.
+    fn next_log_line(
+        &self,
+        lines: &[usize],
+        current: Option<usize>,
+        forward: bool,
+    ) -> Option<usize> {
+        let count = lines.len();
+        if count == 0 {
+            return None;
+        }
+
+        Some(match current {
+            Some(i) if forward => (i + 1) % count,
+            Some(i) => (i + count - 1) % count,
+            None => {
+                let top_line = self.log_top_line();
+                let after = lines.partition_point(|&l| l < top_line);
+                if forward {
+                    after % count
+                } else {
+                    (after + count - 1) % count
+                }
+            }
+        })
+    }
+
+    /// The index of the first log line shown.
+    fn log_top_line(&self) -> usize {
+        match self.job_output_anchor {
+            ScrollAnchor::Top => self.job_output_first_line + self.job_output_offset,
+            ScrollAnchor::Bottom => {
+                let visible_lines = self.job_output_area.height.saturating_sub(2) as usize;
+                let end = self
+                    .job_output
+                    .as_ref()
+                    .map_or(0, |log| log.read().unwrap().end());
+                end.saturating_sub(self.job_output_offset + visible_lines)
+                    .max(self.job_output_first_line)
+            }
+        }
+    }
+
+    /// Scrolls to the tail of the log and keeps it there as lines are added.
+    fn follow_log(&mut self) {
+        self.job_output_anchor = ScrollAnchor::Bottom;
+        self.job_output_offset = 0;
+        self.job_output_follow = true;
+        self.job_output_new_lines = 0;
+    }
+
+    /// Keeps the log where it is as lines are added.
+    fn pause_log(&mut self) {
+        self.job_output_offset = self.log_top_line() - self.job_output_first_line;
+        self.job_output_anchor = ScrollAnchor::Top;
+        self.job_output_follow = false;
+    }
+
+    /// Follows the log when it was scrolled to its tail, and pauses it when it was scrolled away,
+    /// given the scroll position before.
+    fn update_log_follow(&mut self, (anchor, offset): (ScrollAnchor, usize)) {
+        if (anchor, offset) == (self.job_output_anchor, self.job_output_offset) {
+            return;
         }
+        if self.job_output_anchor == ScrollAnchor::Bottom && self.job_output_offset == 0 {
+            self.follow_log();
+        } else if self.job_output_follow || self.job_output_anchor == ScrollAnchor::Bottom {
+            self.pause_log();
+        }
+    }
+
+    fn scroll_to_log_line(&mut self, line: usize) {
+        // show a few lines of context above the line
+        self.job_output_anchor = ScrollAnchor::Top;
+        self.job_output_offset = line
+            .saturating_sub(self.job_output_first_line)
+            .saturating_sub(3);
     }
 
     fn select_next_job(&mut self) {
@@ -1355,96 +3260,81 @@ impl App {
     }
 
     fn update_display_jobs(&mut self) {
+        let matcher = self.fuzzy_matcher();
         self.display_jobs = match &self.view_mode {
-            ViewMode::AllJobs => {
-                use std::collections::HashMap;
-                let mut array_jobs: HashMap<String, Vec<&Job>> = HashMap::new();
-                let mut individual_jobs = Vec::new();
-
-                // Group jobs by array_id
-                for job in &self.jobs {
-                    if job.array_step.is_some() {
-                        array_jobs.entry(job.array_id.clone()).or_insert_with(Vec::new).push(job);
-                    } else {
-                        individual_jobs.push(job);
-                    }
+            ViewMode::AllJobs => job_rows(
+                &self.listed_jobs(),
+                &self.group_by,
+                &self.collapsed_groups,
+                matcher.as_ref(),
+            ),
+            ViewMode::ArrayJobDetails(_) => {
+                let tasks = self.listed_jobs();
+                let tasks = match &matcher {
+                    Some(matcher) => fuzzy_finder::narrow(&tasks, matcher),
+                    None => tasks,
+                };
+                let mut rows: Vec<DisplayJob> = tasks
+                    .into_iter()
+                    .map(|job| DisplayJob::from_job(job, job.id())) // Use the id() method which formats array_id_step properly
+                    .collect();
+                if let Some(matcher) = &matcher {
+                    fuzzy_finder::highlight(&mut rows, matcher);
                 }
+                rows
+            }
+        };
+    }
 
-                let mut display_jobs = Vec::new();
-
-                // Add individual jobs
-                for job in individual_jobs {
-                    display_jobs.push(DisplayJob {
-                        job_id: job.job_id.clone(),
-                        array_id: job.array_id.clone(),
-                        name: job.name.clone(),
-                        state: job.state.clone(),
-                        state_compact: job.state_compact.clone(),
-                        reason: job.reason.clone(),
-                        user: job.user.clone(),
-                        time: job.time.clone(),
-                        tres: job.tres.clone(),
-                        partition: job.partition.clone(),
-                        nodelist: job.nodelist.clone(),
-                        command: job.command.clone(),
-                        is_array: false,
-                        task_count: None,
-                        stdout: job.stdout.clone(),
-                        stderr: job.stderr.clone(),
-                    });
+    /// The jobs the job list is built from: the ones matching the filter or, while viewing an
+    /// array job, its tasks matching it.
+    fn listed_jobs(&self) -> Vec<&Job> {
+        self.jobs
+            .iter()
+            .filter(|job| match &self.view_mode {
+                ViewMode::AllJobs => true,
+                ViewMode::ArrayJobDetails(array_id) => {
+                    job.array_id == *array_id && job.array_step.is_some()
                 }
+            })
+            .filter(|job| self.filter_matches(job))
+            .collect()
+    }
 
-                // Add collapsed array jobs
-                for (array_id, jobs) in array_jobs {
-                    if let Some(first_job) = jobs.first() {
-                        display_jobs.push(DisplayJob {
-                            job_id: array_id.clone(),
-                            array_id: array_id,
-                            name: first_job.name.clone(),
-                            state: first_job.state.clone(),
-                            state_compact: first_job.state_compact.clone(),
-                            reason: first_job.reason.clone(),
-                            user: first_job.user.clone(),
-                            time: first_job.time.clone(),
-                            tres: first_job.tres.clone(),
-                            partition: first_job.partition.clone(),
-                            nodelist: first_job.nodelist.clone(),
-                            command: first_job.command.clone(),
-                            is_array: true,
-                            task_count: Some(jobs.len()),
-                            stdout: first_job.stdout.clone(),
-                            stderr: first_job.stderr.clone(),
-                        });
-                    }
-                }
+    fn filter_matches(&self, job: &Job) -> bool {
+        self.filter.as_ref().is_none_or(|f| f.matches(job))
+    }
 
-                display_jobs
-            },
-            ViewMode::ArrayJobDetails(array_id) => {
-                // Filter jobs to show only tasks from the specific array
-                self.jobs.iter()
-                    .filter(|job| job.array_id == *array_id && job.array_step.is_some())
-                    .map(|job| DisplayJob {
-                        job_id: job.id(), // Use the id() method which formats array_id_step properly
-                        array_id: job.array_id.clone(),
-                        name: job.name.clone(),
-                        state: job.state.clone(),
-                        state_compact: job.state_compact.clone(),
-                        reason: job.reason.clone(),
-                        user: job.user.clone(),
-                        time: job.time.clone(),
-                        tres: job.tres.clone(),
-                        partition: job.partition.clone(),
-                        nodelist: job.nodelist.clone(),
-                        command: job.command.clone(),
-                        is_array: false,
-                        task_count: None,
-                        stdout: job.stdout.clone(),
-                        stderr: job.stderr.clone(),
-                    })
-                    .collect()
-            }
+    fn set_group_by(&mut self, keys: Vec<GroupKey>) {
+        self.group_by = keys;
+        self.update_display_jobs();
+        self.job_list_state.select(Some(0));
+        self.update_job_list_scrollbar();
+    }
+
+    /// Collapses or expands the selected group. Returns `false` if the selection is not a group.
+    fn toggle_group_collapse(&mut self) -> bool {
+        let Some(group) = self
+            .job_list_state
+            .selected()
+            .and_then(|i| self.display_jobs.get(i))
+            .and_then(|j| j.group.as_ref())
+        else {
+            return false;
         };
+
+        if !self.collapsed_groups.remove(&group.path) {
+            self.collapsed_groups.insert(group.path.clone());
+        }
+        self.update_display_jobs();
+        self.update_job_list_scrollbar();
+        true
+    }
+
+    fn enter_selected(&mut self) {
+        if !self.toggle_group_collapse() {
+            self.enter_array_job();
+        }
     }
 
     fn enter_array_job(&mut self) {
@@ -1483,6 +3373,7 @@ impl App {
     }
 
     fn scroll_job_output_up(&mut self) {
+        let position = (self.job_output_anchor, self.job_output_offset);
         let delta = 3; // Scroll 3 lines at a time
         match self.job_output_anchor {
             ScrollAnchor::Top => {
@@ -1492,9 +3383,11 @@ impl App {
                 self.job_output_offset = self.job_output_offset.saturating_add(delta);
             }
         }
+        self.update_log_follow(position);
     }
 
     fn scroll_job_output_down(&mut self) {
+        let position = (self.job_output_anchor, self.job_output_offset);
         let delta = 3; // Scroll 3 lines at a time
         match self.job_output_anchor {
             ScrollAnchor::Top => {
@@ -1504,5 +3397,181 @@ impl App {
                 self.job_output_offset = self.job_output_offset.saturating_sub(delta);
             }
         }
+        self.update_log_follow(position);
+    }
+}
+
+/// The job list rows of `jobs`, narrowed to the jobs matching the fuzzy finder's `matcher`. The
+/// groups are built from the matches, so they count only those, and are all expanded while
+/// searching, so that no match is hidden.
+fn job_rows(
+    jobs: &[&Job],
+    group_by: &[GroupKey],
+    collapsed: &HashSet<String>,
+    matcher: Option<&Matcher>,
+) -> Vec<DisplayJob> {
+    let Some(matcher) = matcher else {
+        return grouping::group_jobs(jobs, group_by, collapsed);
+    };
+    let mut rows = grouping::group_jobs(
+        &fuzzy_finder::narrow(jobs, matcher),
+        group_by,
+        &HashSet::new(),
+    );
+    fuzzy_finder::highlight(&mut rows, matcher);
+    rows
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn job(id: &str, user: &str, name: &str) -> Job {
+        Job {
+            job_id: id.to_owned(),
+            array_id: id.to_owned(),
+            user: user.to_owned(),
+            name: name.to_owned(),
+            ..Job::default()
+        }
+    }
+
+    fn ids(rows: &[DisplayJob]) -> Vec<String> {
+        rows.iter()
+            .map(|r| match &r.group {
+                Some(group) => format!("{} ({})", group.path, group.job_count),
+                None => r.id(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_job_rows_narrowed() {
+        let jobs = [
+            job("1", "alice", "train"),
+            job("2", "alice", "eval"),
+            job("3", "bob", "train"),
+        ];
+        let jobs: Vec<&Job> = jobs.iter().collect();
+        let by_user = [GroupKey::User];
+        let collapsed = HashSet::from(["user=alice".to_owned()]);
+        assert_eq!(
+            ids(&job_rows(&jobs, &by_user, &collapsed, None)),
+            ["user=alice (2)", "user=bob (1)", "3"]
+        );
+
+        // the groups count the matches, and a match in a collapsed group is shown
+        let matcher = Matcher::new("train", MatchMode::Substring).unwrap();
+        let rows = job_rows(&jobs, &by_user, &collapsed, Some(&matcher));
+        assert_eq!(ids(&rows), ["user=alice (1)", "1", "user=bob (1)", "3"]);
+        assert_eq!(rows[1].highlights.get(Column::Name), &[0, 1, 2, 3, 4]);
+
+        let matcher = Matcher::new("^", MatchMode::Regex).unwrap();
+        assert_eq!(job_rows(&jobs, &[], &collapsed, Some(&matcher)).len(), 3);
+    }
+
+    fn app() -> App {
+        let (_, input_receiver) = unbounded();
+        let (input_pause, _) = unbounded();
+        let config = Config::default();
+        let log_highlighter = LogHighlighter::new(&config.log).unwrap();
+        let notifier = Notifier::new(config.notifications.clone(), &config.hooks).unwrap();
+        // squeue is run by the job watcher's thread, whose jobs aren't waited for
+        App::new(
+            input_receiver,
+            input_pause,
+            3600,
+            3600,
+            Vec::new(),
+            Vec::new(),
+            None,
+            config,
+            log_highlighter,
+            notifier,
+        )
+    }
+
+    fn type_keys(app: &mut App, keys: &str) {
+        for c in keys.chars() {
+            app.handle(AppMessage::Key(KeyEvent::new(
+                KeyCode::Char(c),
+                KeyModifiers::NONE,
+            )));
+        }
+    }
+
+    #[test]
+    fn test_q_in_inputs() {
+        let mut app = app();
+        type_keys(&mut app, "X");
+        for _ in "jobs.csv".chars() {
+            app.handle(AppMessage::Key(KeyEvent::new(
+                KeyCode::Backspace,
+                KeyModifiers::NONE,
+            )));
+        }
+        type_keys(&mut app, "/tmp/q.csv");
+        assert!(!app.quit);
+        assert!(matches!(&app.dialog, Some(Dialog::Export { path, .. }) if path == "/tmp/q.csv"));
+        app.handle(AppMessage::Key(KeyEvent::new(
+            KeyCode::Esc,
+            KeyModifiers::NONE,
+        )));
+
+        type_keys(&mut app, "/quux");
+        assert!(!app.quit);
+        assert_eq!(app.fuzzy_finder_input, "quux");
+        app.handle(AppMessage::Key(KeyEvent::new(
+            KeyCode::Esc,
+            KeyModifiers::NONE,
+        )));
+
+        type_keys(&mut app, "q");
+        assert!(app.quit);
+    }
+
+    #[test]
+    fn test_cancel_listed_jobs() {
+        let mut app = app();
+        let task = |step: &str, name: &str| Job {
+            job_id: format!("9_{}", step),
+            array_id: "9".to_owned(),
+            array_step: Some(step.to_owned()),
+            ..job("", "alice", name)
+        };
+        app.jobs = vec![
+            job("1", "bob", "train"),
+            task("1", "train"),
+            task("2", "train"),
+            task("3", "eval"),
+        ];
+        app.fuzzy_finder_mode = MatchMode::Substring;
+        app.fuzzy_finder_input = "train".to_owned();
+        app.update_display_jobs();
+        app.confirm_cancel_listed_jobs();
+        assert!(
+            matches!(&app.dialog, Some(Dialog::ConfirmCancelJobs(ids)) if ids == &["1", "9_1", "9_2"])
+        );
+    }
+
+    #[test]
+    fn test_attach_without_node() {
+        let mut app = app();
+        app.jobs = vec![Job {
+            state_compact: "R".to_owned(),
+            ..job("1", "alice", "train")
+        }];
+        app.update_display_jobs();
+        app.job_list_state.select(Some(0));
+        type_keys(&mut app, "a");
+        assert!(app.suspended_command.is_none());
+        assert_eq!(app.status_message.as_deref(), Some("no node known for 1"));
+
+        app.jobs[0].nodelist = "gpu[01-02]".to_owned();
+        app.update_display_jobs();
+        type_keys(&mut app, "a");
+        assert!(
+            matches!(&app.dialog, Some(Dialog::AttachNode { nodes, .. }) if nodes == &["gpu01", "gpu02"])
+        );
     }
 }
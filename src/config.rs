@@ -0,0 +1,329 @@
+use std::{
+    collections::BTreeMap,
+    env, fs, io,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::notifications::JobEvent;
+
+/// Named filter expressions, stored in `filters.toml` in the config directory.
+pub type SavedFilters = BTreeMap<String, String>;
+
+/// Settings read from `config.toml` in the config directory.
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub log: LogConfig,
+    pub attach: AttachConfig,
+    pub notifications: NotificationConfig,
+    pub hooks: Vec<HookConfig>,
+    /// Kept in a separate file, since turf writes it.
+    #[serde(skip)]
+    pub saved_filters: SavedFilters,
+    /// Why the saved filters couldn't be loaded; turf starts without them then.
+    #[serde(skip)]
+    pub saved_filters_error: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LogConfig {
+    /// Use the built-in highlight patterns in addition to `highlight`.
+    pub default_highlights: bool,
+    pub highlight: Vec<HighlightRule>,
+    /// Keep at most this many lines of a log in memory, dropping the oldest ones.
+    pub max_lines: Option<usize>,
+    /// Filename patterns of logs to look for when a job's output file doesn't exist, with sbatch's
+    /// replacement symbols and `*`/`?` wildcards in the file name.
+    pub search: Vec<String>,
+    /// How to read logs that are only visible on the node a job runs on.
+    pub remote: Option<RemoteConfig>,
+}
+
+impl Default for LogConfig {
+    fn default() -> Self {
+        LogConfig {
+            default_highlights: true,
+            highlight: Vec::new(),
+            max_lines: None,
+            search: Vec::new(),
+            remote: None,
+        }
+    }
+}
+
+/// Logs of running jobs are read with `command`, a shell command template with `{node}`, `{id}`,
+/// `{path}` and `{offset}`, if they are under one of `paths`, or, without `paths`, if they don't
+/// exist here.
+#[derive(Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RemoteConfig {
+    pub command: String,
+    #[serde(default)]
+    pub paths: Vec<PathBuf>,
+}
+
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AttachConfig {
+    /// The shell command that opens an interactive shell in a running job's allocation, with
+    /// `{id}` and `{node}` replaced by the job and the node picked from its node list.
+    pub command: String,
+}
+
+impl Default for AttachConfig {
+    fn default() -> Self {
+        AttachConfig {
+            command: "srun --jobid={id} --overlap --pty --nodes=1 --nodelist={node} bash"
+                .to_string(),
+        }
+    }
+}
+
+/// How to tell about job state changes while turf runs.
+#[derive(Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct NotificationConfig {
+    /// The events to tell about, all by default.
+    pub events: Vec<JobEvent>,
+    /// Ring the terminal bell, which e.g. tmux shows on the window.
+    pub bell: bool,
+    /// Send a desktop notification through the terminal.
+    pub osc: Option<OscNotification>,
+    /// A shell command to run, with the job in `TURF_*` environment variables.
+    pub command: Option<String>,
+}
+
+impl Default for NotificationConfig {
+    fn default() -> Self {
+        NotificationConfig {
+            events: JobEvent::ALL.to_vec(),
+            bell: false,
+            osc: None,
+            command: None,
+        }
+    }
+}
+
+/// A shell command to run in the background when a job has one of the events in `on`, like
+/// the notification command. `name` is a regular expression the job's name has to match, and
+/// `partitions` limits it to jobs in those partitions.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct HookConfig {
+    pub on: Vec<JobEvent>,
+    pub name: Option<String>,
+    #[serde(default)]
+    pub partitions: Vec<String>,
+    pub command: String,
+}
+
+/// The escape sequence for desktop notifications the terminal understands.
+#[derive(Clone, Copy, Deserialize)]
+pub enum OscNotification {
+    /// iTerm2, Windows Terminal, kitty, WezTerm, ...
+    #[serde(rename = "9")]
+    Osc9,
+    /// rxvt-unicode, foot, Ghostty, ...
+    #[serde(rename = "777")]
+    Osc777,
+}
+
+/// Log lines matching `pattern` are colored (red for errors and yellow for warnings, unless
+/// `color` is set) and can be jumped to.
+#[derive(Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct HighlightRule {
+    pub pattern: String,
+    #[serde(default)]
+    pub level: Level,
+    pub color: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Level {
+    #[default]
+    Error,
+    Warning,
+}
+
+/// Loads `config.toml` and the saved filters. Missing files are treated as empty.
+pub fn load() -> io::Result<Config> {
+    load_from(config_dir().as_deref())
+}
+
+fn load_from(dir: Option<&Path>) -> io::Result<Config> {
+    let mut config: Config = match dir.map(|d| fs::read_to_string(d.join("config.toml"))) {
+        Some(Ok(s)) => toml::from_str(&s).map_err(|e| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("invalid config.toml: {}", e),
+            )
+        })?,
+        Some(Err(e)) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+        _ => Config::default(),
+    };
+    // filters.toml is written by turf, so a broken one shouldn't keep it from starting
+    match dir
+        .map(|d| load_saved_filters(&d.join("filters.toml")))
+        .transpose()
+    {
+        Ok(filters) => config.saved_filters = filters.unwrap_or_default(),
+        Err(e) => {
+            // the first line of a TOML error has the position, which fits in the status line
+            let e = e.to_string();
+            config.saved_filters_error = Some(format!(
+                "failed to load saved filters: {}",
+                e.lines().next().unwrap_or(&e)
+            ));
+        }
+    }
+    Ok(config)
+}
+
+/// turf's configuration directory, `$XDG_CONFIG_HOME/turf` or `~/.config/turf`.
+pub fn config_dir() -> Option<PathBuf> {
+    env::var_os("XDG_CONFIG_HOME")
+        .filter(|d| !d.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|h| PathBuf::from(h).join(".config")))
+        .map(|d| d.join("turf"))
+}
+
+/// Where the output of hook commands is written.
+pub fn hook_log_path() -> Option<PathBuf> {
+    config_dir().map(|d| d.join("hooks.log"))
+}
+
+fn saved_filters_path() -> io::Result<PathBuf> {
+    config_dir()
+        .map(|d| d.join("filters.toml"))
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no config directory"))
+}
+
+fn load_saved_filters(path: &Path) -> io::Result<SavedFilters> {
+    match fs::read_to_string(path) {
+        Ok(s) => toml::from_str(&s).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(SavedFilters::new()),
+        Err(e) => Err(e),
+    }
+}
+
+/// Adds a saved filter. One with the same name is only replaced with `overwrite`; otherwise its
+/// expression is returned.
+pub fn add_saved_filter(
+    filters: &mut SavedFilters,
+    name: &str,
+    filter: String,
+    overwrite: bool,
+) -> Result<(), String> {
+    match filters.get(name) {
+        Some(existing) if !overwrite && *existing != filter => Err(existing.clone()),
+        _ => {
+            filters.insert(name.to_owned(), filter);
+            Ok(())
+        }
+    }
+}
+
+pub fn save_filters(filters: &SavedFilters) -> io::Result<()> {
+    let path = saved_filters_path()?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let s = toml::to_string(filters).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    fs::write(path, s)
+}
+
+/// How many scripts the submit form remembers.
+const MAX_RECENT_SCRIPTS: usize = 20;
+
+#[derive(Default, Deserialize, Serialize)]
+struct RecentScripts {
+    scripts: Vec<PathBuf>,
+}
+
+fn recent_scripts_path() -> io::Result<PathBuf> {
+    config_dir()
+        .map(|d| d.join("recent_scripts.toml"))
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no config directory"))
+}
+
+/// The scripts submitted from turf, most recent first.
+pub fn load_recent_scripts() -> io::Result<Vec<PathBuf>> {
+    match fs::read_to_string(recent_scripts_path()?) {
+        Ok(s) => toml::from_str::<RecentScripts>(&s)
+            .map(|r| r.scripts)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(e),
+    }
+}
+
+/// Moves `script` to the front of the recent scripts.
+pub fn add_recent_script(script: PathBuf) -> io::Result<()> {
+    let mut scripts = load_recent_scripts().unwrap_or_default();
+    scripts.retain(|s| *s != script);
+    scripts.insert(0, script);
+    scripts.truncate(MAX_RECENT_SCRIPTS);
+
+    let path = recent_scripts_path()?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let s = toml::to_string(&RecentScripts { scripts })
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    fs::write(path, s)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_broken_saved_filters() {
+        let dir = env::temp_dir().join(format!("turf-test-config-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let config = load_from(Some(&dir)).unwrap();
+        assert!(config.saved_filters.is_empty() && config.saved_filters_error.is_none());
+
+        fs::write(dir.join("filters.toml"), "gpu = \"partition:gpu\"\n").unwrap();
+        let config = load_from(Some(&dir)).unwrap();
+        assert_eq!(config.saved_filters["gpu"], "partition:gpu");
+
+        fs::write(dir.join("filters.toml"), "gpu = partition:gpu\n").unwrap();
+        let config = load_from(Some(&dir)).unwrap();
+        assert!(config.saved_filters.is_empty());
+        assert!(config
+            .saved_filters_error
+            .unwrap()
+            .starts_with("failed to load saved filters: "));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_add_saved_filter() {
+        let mut filters = SavedFilters::new();
+        assert_eq!(
+            add_saved_filter(&mut filters, "gpu", "partition:gpu".to_owned(), false),
+            Ok(())
+        );
+        assert_eq!(
+            add_saved_filter(&mut filters, "gpu", "partition:gpu".to_owned(), false),
+            Ok(())
+        );
+        assert_eq!(
+            add_saved_filter(&mut filters, "gpu", "gpus>0".to_owned(), false),
+            Err("partition:gpu".to_owned())
+        );
+        assert_eq!(filters["gpu"], "partition:gpu");
+        assert_eq!(
+            add_saved_filter(&mut filters, "gpu", "gpus>0".to_owned(), true),
+            Ok(())
+        );
+        assert_eq!(filters["gpu"], "gpus>0");
+    }
+}
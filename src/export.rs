@@ -0,0 +1,339 @@
+use std::{fmt::Write, path::Path};
+
+use clap::ValueEnum;
+
+use crate::ansi;
+use crate::app::{DisplayJob, Job};
+use crate::log_buffer::line_bytes;
+
+/// How many lines of the log a job report ends with.
+const REPORT_TAIL_LINES: usize = 50;
+
+/// The escape sequence that puts `text` on the system clipboard (OSC 52). The terminal sets the
+/// clipboard of the machine it runs on, so this also works over SSH.
+pub fn osc52(text: &str) -> String {
+    format!("\x1b]52;c;{}\x07", base64(text.as_bytes()))
+}
+
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut s = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, &b)| n | (b as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                s.push(ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                s.push('=');
+            }
+        }
+    }
+    s
+}
+
+/// Log lines as plain text, without escape sequences and with bytes that aren't UTF-8 replaced.
+pub fn plain_lines<'a>(lines: impl IntoIterator<Item = &'a str>) -> String {
+    lines
+        .into_iter()
+        .map(|l| String::from_utf8_lossy(&line_bytes(&ansi::strip(l))).into_owned() + "\n")
+        .collect()
+}
+
+/// A Markdown report of a job with its details and the end of its log, to paste into a ticket.
+pub fn job_report<'a>(
+    job: &DisplayJob,
+    log_name: &str,
+    log: impl DoubleEndedIterator<Item = &'a str>,
+) -> String {
+    let mut report = format!(
+        "# Job {}: {}\n\n| Field | Value |\n| --- | --- |\n",
+        job.id(),
+        job.name
+    );
+    let state = match &job.reason {
+        Some(reason) => format!("{} ({})", job.state, reason),
+        None => job.state.clone(),
+    };
+    let path = |p: &Option<std::path::PathBuf>| {
+        p.as_ref()
+            .map(|p| p.display().to_string())
+            .unwrap_or_default()
+    };
+    for (field, value) in [
+        ("State", state),
+        ("User", job.user.clone()),
+        ("Account", job.account.clone()),
+        ("Partition", job.partition.clone()),
+        ("Time", job.time.clone()),
+        ("Nodes", job.nodelist.clone()),
+        ("TRES", job.tres.clone()),
+        ("Command", job.command.clone()),
+        ("stdout", path(&job.stdout)),
+        ("stderr", path(&job.stderr)),
+    ] {
+        if !value.is_empty() {
+            writeln!(report, "| {} | `{}` |", field, value.replace('|', "\\|")).unwrap();
+        }
+    }
+
+    let mut tail: Vec<&str> = log.rev().take(REPORT_TAIL_LINES).collect();
+    tail.reverse();
+    let tail = plain_lines(tail);
+    // a fence longer than any run of backticks in the log
+    let fence = "`".repeat((3..).find(|&n| !tail.contains(&"`".repeat(n))).unwrap());
+    write!(
+        report,
+        "\n## Last lines of {}\n\n{}\n{}{}\n",
+        log_name, fence, tail, fence
+    )
+    .unwrap();
+    report
+}
+
+/// A file format for a list of jobs.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum JobsFormat {
+    /// An array of objects, one per line.
+    Json,
+    /// A header row and a row per job.
+    Csv,
+    /// Like CSV, separated by tabs, with backslashes, tabs and line breaks in values escaped as
+    /// `\\`, `\t`, `\n` and `\r`.
+    Tsv,
+}
+
+impl JobsFormat {
+    /// The format for a file name's extension.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()?.to_ascii_lowercase().as_str() {
+            "json" => Some(JobsFormat::Json),
+            "csv" => Some(JobsFormat::Csv),
+            "tsv" | "tab" => Some(JobsFormat::Tsv),
+            _ => None,
+        }
+    }
+}
+
+/// The fields of a job in the order they are exported, `None` for the ones it doesn't have.
+fn job_fields(job: &Job) -> [(&'static str, Option<String>); 20] {
+    let path = |p: &Option<std::path::PathBuf>| p.as_ref().map(|p| p.display().to_string());
+    [
+        ("job_id", Some(job.job_id.clone())),
+        ("array_job_id", Some(job.array_id.clone())),
+        ("array_task_id", job.array_step.clone()),
+        ("name", Some(job.name.clone())),
+        ("state", Some(job.state.clone())),
+        ("state_compact", Some(job.state_compact.clone())),
+        ("reason", job.reason.clone()),
+        ("user", Some(job.user.clone())),
+        ("account", Some(job.account.clone())),
+        ("partition", Some(job.partition.clone())),
+        ("time", Some(job.time.clone())),
+        ("tres", Some(job.tres.clone())),
+        ("nodelist", Some(job.nodelist.clone())),
+        ("command", Some(job.command.clone())),
+        ("stdout", path(&job.stdout)),
+        ("stderr", path(&job.stderr)),
+        ("stdout_error", job.stdout_error.clone()),
+        ("stderr_error", job.stderr_error.clone()),
+        (
+            "stdout_source",
+            job.stdout_source.as_ref().map(|s| s.to_string()),
+        ),
+        (
+            "stderr_source",
+            job.stderr_source.as_ref().map(|s| s.to_string()),
+        ),
+    ]
+}
+
+/// The jobs in `format`, with all their fields including the resolved output files.
+pub fn jobs<'a>(jobs: impl IntoIterator<Item = &'a Job>, format: JobsFormat) -> String {
+    let mut out = String::new();
+    match format {
+        JobsFormat::Json => {
+            let objects: Vec<String> = jobs
+                .into_iter()
+                .map(|job| {
+                    let fields = job_fields(job).map(|(name, value)| {
+                        format!(
+                            "\"{}\": {}",
+                            name,
+                            value.map_or("null".to_string(), |v| json_string(&v))
+                        )
+                    });
+                    format!("{{{}}}", fields.join(", "))
+                })
+                .collect();
+            if objects.is_empty() {
+                out.push_str("[]\n");
+            } else {
+                write!(out, "[\n  {}\n]\n", objects.join(",\n  ")).unwrap();
+            }
+        }
+        JobsFormat::Csv | JobsFormat::Tsv => {
+            let (separator, field): (&str, fn(&str) -> String) = match format {
+                JobsFormat::Tsv => ("\t", tsv_field),
+                _ => (",", csv_field),
+            };
+            let header = job_fields(&Job::default()).map(|(name, _)| name);
+            writeln!(out, "{}", header.join(separator)).unwrap();
+            for job in jobs {
+                let row = job_fields(job).map(|(_, value)| field(&value.unwrap_or_default()));
+                writeln!(out, "{}", row.join(separator)).unwrap();
+            }
+        }
+    }
+    out
+}
+
+/// The jobs of job list rows, in their order. Group header rows are skipped, and a collapsed array
+/// row stands for its tasks in `listed`, the jobs the rows were built from.
+pub fn row_jobs<'a>(rows: &[DisplayJob], listed: &[&'a Job]) -> Vec<&'a Job> {
+    let mut row_jobs = Vec::new();
+    for row in rows.iter().filter(|r| r.group.is_none()) {
+        if row.is_array {
+            row_jobs.extend(
+                listed
+                    .iter()
+                    .filter(|j| j.array_id == row.array_id && j.array_step.is_some()),
+            );
+        } else {
+            row_jobs.extend(listed.iter().find(|j| j.id() == row.job_id));
+        }
+    }
+    row_jobs
+}
+
+fn json_string(s: &str) -> String {
+    let mut json = String::with_capacity(s.len() + 2);
+    json.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if c.is_control() => write!(json, "\\u{:04x}", c as u32).unwrap(),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
+
+/// A TSV field, which can't contain tabs or line breaks.
+fn tsv_field(s: &str) -> String {
+    s.replace('\\', "\\\\")
+        .replace('\t', "\\t")
+        .replace('\n', "\\n")
+        .replace('\r', "\\r")
+}
+
+/// A CSV field, quoted if it has to be (RFC 4180).
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::log_buffer::byte_char;
+
+    #[test]
+    fn test_osc52() {
+        assert_eq!(base64(b""), "");
+        assert_eq!(base64(b"f"), "Zg==");
+        assert_eq!(base64(b"fo"), "Zm8=");
+        assert_eq!(base64(b"foo"), "Zm9v");
+        assert_eq!(base64(b"foobar"), "Zm9vYmFy");
+        assert_eq!(osc52("12345"), "\x1b]52;c;MTIzNDU=\x07");
+    }
+
+    #[test]
+    fn test_jobs() {
+        let job = Job {
+            job_id: "7".to_owned(),
+            name: "say \"hi\", then\tquit".to_owned(),
+            stdout: Some("/logs/7.out".into()),
+            ..Job::default()
+        };
+        let json = jobs([&job], JobsFormat::Json);
+        assert!(
+            json.starts_with(
+                "[\n  {\"job_id\": \"7\", \"array_job_id\": \"\", \"array_task_id\": null, "
+            ),
+            "{}",
+            json
+        );
+        assert!(
+            json.contains(r#""name": "say \"hi\", then\tquit""#),
+            "{}",
+            json
+        );
+        assert!(json.contains(r#""stdout": "/logs/7.out""#), "{}", json);
+        assert_eq!(jobs([], JobsFormat::Json), "[]\n");
+
+        let csv = jobs([&job], JobsFormat::Csv);
+        let lines: Vec<&str> = csv.lines().collect();
+        assert!(lines[0].starts_with("job_id,array_job_id,array_task_id,name,state,"));
+        assert!(
+            lines[1].starts_with("7,,,\"say \"\"hi\"\", then\tquit\","),
+            "{}",
+            lines[1]
+        );
+        assert_eq!(lines.len(), 2);
+
+        let tsv = jobs([&job], JobsFormat::Tsv);
+        let lines: Vec<&str> = tsv.lines().collect();
+        assert!(lines[0].starts_with("job_id\tarray_job_id\tarray_task_id\tname\t"));
+        assert!(
+            lines[1].starts_with("7\t\t\tsay \"hi\", then\\tquit\t"),
+            "{}",
+            lines[1]
+        );
+        assert_eq!(jobs([], JobsFormat::Csv).lines().count(), 1);
+    }
+
+    #[test]
+    fn test_row_jobs() {
+        let job = |id: &str, array_id: &str, step: Option<&str>, state: &str| Job {
+            job_id: id.to_owned(),
+            array_id: array_id.to_owned(),
+            array_step: step.map(str::to_owned),
+            state: state.to_owned(),
+            ..Job::default()
+        };
+        let jobs = [
+            job("1", "1", None, "R"),
+            job("2_1", "2", Some("1"), "R"),
+            job("2_2", "2", Some("2"), "PD"),
+            job("2_3", "2", Some("3"), "R"),
+        ];
+        let listed: Vec<&Job> = jobs.iter().filter(|j| j.state == "R").collect();
+        let rows = crate::grouping::group_jobs(
+            &listed,
+            &[crate::grouping::GroupKey::User],
+            &Default::default(),
+        );
+        let ids: Vec<String> = row_jobs(&rows, &listed).iter().map(|j| j.id()).collect();
+        assert_eq!(ids, ["1", "2_1", "2_3"]);
+    }
+
+    #[test]
+    fn test_plain_lines() {
+        let latin1 = format!("caf{}", byte_char(0xe9));
+        assert_eq!(
+            plain_lines(["\x1b[31mred\x1b[0m", "50%\r100%", &latin1]),
+            "red\n100%\ncaf\u{fffd}\n"
+        );
+    }
+}
@@ -1,42 +1,154 @@
 use std::{
+    collections::HashMap,
     fmt,
-    fs::File,
+    fs::{File, Metadata},
     io::{self, Read, Seek},
     path::{Path, PathBuf},
+    process::Command,
     thread,
     time::Duration,
 };
 
 use crossbeam::{
-    channel::{unbounded, Receiver, RecvError, SendError, Sender},
+    channel::{bounded, unbounded, Receiver, RecvError, SendError, Sender},
     select,
 };
-use notify::{event::ModifyKind, RecursiveMode, Watcher};
+use notify::{event::ModifyKind, RecommendedWatcher, RecursiveMode, Watcher};
 
 use crate::app::AppMessage;
+use crate::config::RemoteConfig;
+use crate::log_buffer::{decode_lossy, LogBuffer, LogUpdate, SharedLogBuffer};
+
+const READ_CHUNK_SIZE: usize = 1 << 20;
+/// How much of the start of the file is compared to detect that it was rewritten.
+const HEAD_LEN: usize = 64;
+/// Starts the stderr lines of a merged log, to color them.
+const MERGED_TAG: &str = "\x1b[31m";
+
+/// A log file to read, either locally or on the node the job runs on.
+#[derive(Clone, PartialEq)]
+pub struct LogFile {
+    pub path: PathBuf,
+    /// The shell command that prints the file from the byte given by `{offset}` on, for a file that
+    /// isn't visible here, see [`remote_command`].
+    pub remote_command: Option<String>,
+}
+
+impl LogFile {
+    /// How to read the log at `path` of the job `id` running on `node`: through the configured
+    /// remote command if the file is on the node, otherwise directly.
+    pub fn new(path: &Path, id: &str, node: Option<&str>, remote: Option<&RemoteConfig>) -> Self {
+        let remote = remote.zip(node).filter(|(remote, _)| {
+            if remote.paths.is_empty() {
+                !path.exists()
+            } else {
+                remote.paths.iter().any(|p| path.starts_with(p))
+            }
+        });
+        LogFile {
+            path: path.to_owned(),
+            remote_command: remote
+                .map(|(remote, node)| remote_command(&remote.command, node, id, path)),
+        }
+    }
+}
+
+/// Fills in `{node}`, `{id}` and `{path}` of a remote log command template, quoted for the shell.
+/// `{offset}` is left for the reader: the 1-based position of the first byte it hasn't read yet,
+/// as for `tail -c +{offset}`.
+fn remote_command(template: &str, node: &str, id: &str, path: &Path) -> String {
+    template
+        .replace("{node}", &shell_quote(node))
+        .replace("{id}", &shell_quote(id))
+        .replace("{path}", &shell_quote(&path.to_string_lossy()))
+}
+
+/// Quotes `s` as a single word for `sh`.
+pub fn shell_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', "'\\''"))
+}
+
+/// Where a file reader puts what it reads.
+enum ReadTarget {
+    /// A log of its own, which is shown by itself.
+    Own(SharedLogBuffer),
+    /// A log shared with the other files, which gets each complete line starting with `tag`, in the
+    /// order they are read: first what each file has when it's opened, one file after the other,
+    /// then what is written to them since. The rest of an unfinished line is kept in `partial`.
+    Merged {
+        buffer: SharedLogBuffer,
+        tag: &'static str,
+        partial: String,
+    },
+}
+
+impl ReadTarget {
+    fn buffer(&self) -> &SharedLogBuffer {
+        match self {
+            ReadTarget::Own(buffer) | ReadTarget::Merged { buffer, .. } => buffer,
+        }
+    }
+}
 
 struct FileReader {
-    content_sender: Sender<io::Result<String>>,
+    content_sender: Sender<(usize, io::Result<LogUpdate>)>,
+    index: usize, // which of the watched files this is
     receiver: Receiver<()>,
     file_path: PathBuf,
+    /// The command to read the file with instead of opening it, see [`LogFile`].
+    remote_command: Option<String>,
     interval: Duration,
-    content: String,
+    target: ReadTarget,
+    /// Whether the UI has been sent the buffer, which it isn't after a read error.
+    opened: bool,
+    /// Bytes of a UTF-8 sequence cut off at the end of the last read.
+    pending: Vec<u8>,
     pos: u64,
+    /// The first bytes and the inode of the file, to tell when it's truncated or replaced.
+    head: Vec<u8>,
+    inode: Option<u64>,
+    /// Closed by the reader of the previous merged file once it has read that file, so what the
+    /// files have when opened is merged in their order rather than the one the readers run in.
+    previous_read: Option<Receiver<()>>,
+    /// Closed once the file has been read the first time, see `previous_read`.
+    first_read: Option<Sender<()>>,
+}
+
+/// The readers of files shown together, i.e. in the log pane or in the tiles.
+struct ReaderGroup {
+    content_sender: Sender<(usize, io::Result<LogUpdate>)>,
+    content_receiver: Receiver<(usize, io::Result<LogUpdate>)>,
+    readers: Vec<Sender<()>>,
+    /// Readers of remote files, which only poll since changes can't be watched.
+    remote_readers: Vec<Sender<()>>,
+    file_paths: Vec<PathBuf>,
+}
+
+/// The files watched for changes, counted since the log pane and the tiles can show the same file.
+struct Watches {
+    watcher: RecommendedWatcher,
+    counts: HashMap<PathBuf, usize>,
 }
 
 struct FileWatcher {
     app: Sender<AppMessage>,
     receiver: Receiver<FileWatcherMessage>,
-    file_path: Option<PathBuf>,
     interval: Duration,
+    max_lines: Option<usize>,
 }
 pub enum FileWatcherMessage {
-    FilePath(Option<PathBuf>),
+    /// The files to show in the log pane. With `merged`, their lines go into a single log;
+    /// otherwise each file has its own, sent to the app with the file's index.
+    Files(Vec<LogFile>, bool),
+    /// The files of the pinned jobs, each shown in its own tile.
+    TileFiles(Vec<LogFile>),
 }
 
 pub struct FileWatcherHandle {
     sender: Sender<FileWatcherMessage>,
-    file_path: Option<PathBuf>,
+    files: Vec<LogFile>,
+    merged: bool,
+    tile_files: Vec<LogFile>,
 }
 
 pub enum FileWatcherError {
@@ -58,88 +170,219 @@ impl FileWatcher {
         app: Sender<AppMessage>,
         receiver: Receiver<FileWatcherMessage>,
         interval: Duration,
+        max_lines: Option<usize>,
     ) -> Self {
         FileWatcher {
-            app: app,
-            receiver: receiver,
-            file_path: None,
-            interval: interval,
+            app,
+            receiver,
+            interval,
+            max_lines,
         }
     }
 
     fn run(&mut self) -> Result<(), RecvError> {
         let (watch_sender, watch_receiver) = unbounded();
-        let mut watcher = notify::recommended_watcher(move |res: notify::Result<notify::Event>| {
+        let watcher = notify::recommended_watcher(move |res: notify::Result<notify::Event>| {
             let event = res.unwrap();
-            match event.kind {
-                notify::EventKind::Modify(ModifyKind::Data(_)) => {
-                    watch_sender.send(event.paths).unwrap();
-                }
-                _ => {}
+            if let notify::EventKind::Modify(ModifyKind::Data(_)) = event.kind {
+                watch_sender.send(event.paths).unwrap();
             };
         })
         .unwrap();
 
-        let (mut _content_sender, mut _content_receiver) = unbounded::<io::Result<String>>();
-        let (mut _watch_sender, mut _watch_receiver) = unbounded::<()>();
+        let mut watches = Watches {
+            watcher,
+            counts: HashMap::new(),
+        };
+
+        let mut log = ReaderGroup::new();
+        let mut tiles = ReaderGroup::new();
         loop {
             select! {
                 recv(self.receiver) -> msg => {
                     match msg? {
-                        FileWatcherMessage::FilePath(file_path) => {
-                            (_content_sender, _content_receiver) = unbounded();
-                            (_watch_sender, _watch_receiver) = unbounded::<()>();
-
-                            if let Some(p) = &self.file_path {
-                                watcher.unwatch(p).expect(format!("Failed to unwatch {:?}", p).as_str());
-                                self.file_path = None;
-                            }
-
-                            if let Some(p) = file_path {
-                                let res = watcher.watch(Path::new(&p), RecursiveMode::NonRecursive);
-                                match res {
-                                    Ok(_) => {
-                                        self.file_path = Some(p.clone());
-                                        let i = self.interval.clone();
-                                        thread::spawn(move || FileReader::new(_content_sender, _watch_receiver, p, i).run());
-                                    },
-                                    Err(e) => self.app.send(AppMessage::JobOutput(Err(FileWatcherError::Watcher(e)))).unwrap()
-                                };
-                            } else {
-                                _content_sender.send(Ok("".to_string())).unwrap();
-                            }
+                        FileWatcherMessage::Files(files, merged) => {
+                            self.open(&mut log, &mut watches, files, merged, AppMessage::JobOutput);
+                        }
+                        FileWatcherMessage::TileFiles(files) => {
+                            self.open(&mut tiles, &mut watches, files, false, AppMessage::TileOutput);
                         }
                     }
                 }
-                recv(watch_receiver) -> _ => { _watch_sender.send(()).unwrap(); }
-                recv(_content_receiver) -> msg => {
-                    self.app.send(AppMessage::JobOutput(msg.unwrap().map_err(|e| FileWatcherError::File(e)))).unwrap();
+                recv(watch_receiver) -> _ => {
+                    for reader in log.readers.iter().chain(&tiles.readers) {
+                        let _ = reader.send(());
+                    }
+                }
+                recv(log.content_receiver) -> msg => {
+                    let (i, update) = msg.unwrap();
+                    self.app.send(AppMessage::JobOutput(i, update.map_err(FileWatcherError::File))).unwrap();
+                }
+                recv(tiles.content_receiver) -> msg => {
+                    let (i, update) = msg.unwrap();
+                    self.app.send(AppMessage::TileOutput(i, update.map_err(FileWatcherError::File))).unwrap();
                 }
             }
         }
     }
+
+    /// Replaces the readers of `group` with readers of `files`, whose errors to watch a file are
+    /// sent to the app with `message`.
+    fn open(
+        &self,
+        group: &mut ReaderGroup,
+        watches: &mut Watches,
+        files: Vec<LogFile>,
+        merged: bool,
+        message: fn(usize, Result<LogUpdate, FileWatcherError>) -> AppMessage,
+    ) {
+        for p in &group.file_paths {
+            watches.unwatch(p);
+        }
+        // the readers of the previous files stop once their channels are gone
+        *group = ReaderGroup::new();
+
+        let merged_buffer = if merged || files.is_empty() {
+            let buffer = LogBuffer::shared(self.max_lines);
+            group
+                .content_sender
+                .send((0, Ok(LogUpdate::Opened(buffer.clone()))))
+                .unwrap();
+            Some(buffer)
+        } else {
+            None
+        };
+
+        let mut previous_read = None;
+        for (
+            i,
+            LogFile {
+                path: p,
+                remote_command,
+            },
+        ) in files.into_iter().enumerate()
+        {
+            let (watch_sender, watch_receiver) = unbounded();
+            if remote_command.is_some() {
+                group.remote_readers.push(watch_sender);
+            } else {
+                if let Err(e) = watches.watch(&p) {
+                    // a merged log shows whatever files there are
+                    if merged_buffer.is_none() {
+                        self.app
+                            .send(message(i, Err(FileWatcherError::Watcher(e))))
+                            .unwrap();
+                    }
+                    continue;
+                }
+                group.file_paths.push(p.clone());
+                group.readers.push(watch_sender);
+            }
+            let (index, target) = match &merged_buffer {
+                Some(buffer) => (
+                    0,
+                    ReadTarget::Merged {
+                        buffer: buffer.clone(),
+                        tag: if i == 0 { "" } else { MERGED_TAG },
+                        partial: String::new(),
+                    },
+                ),
+                None => (i, ReadTarget::Own(LogBuffer::shared(self.max_lines))),
+            };
+            let content_sender = group.content_sender.clone();
+            let interval = self.interval;
+            let mut reader = FileReader::new(
+                content_sender,
+                index,
+                watch_receiver,
+                p,
+                remote_command,
+                interval,
+                target,
+            );
+            if merged_buffer.is_some() {
+                let (first_read, read) = bounded(0);
+                reader.previous_read = previous_read.replace(read);
+                reader.first_read = Some(first_read);
+            }
+            thread::spawn(move || reader.run());
+        }
+    }
+}
+
+impl ReaderGroup {
+    fn new() -> Self {
+        let (content_sender, content_receiver) = unbounded();
+        ReaderGroup {
+            content_sender,
+            content_receiver,
+            readers: Vec::new(),
+            remote_readers: Vec::new(),
+            file_paths: Vec::new(),
+        }
+    }
+}
+
+impl Watches {
+    fn watch(&mut self, p: &Path) -> notify::Result<()> {
+        if let Some(count) = self.counts.get_mut(p) {
+            *count += 1;
+            return Ok(());
+        }
+        self.watcher.watch(p, RecursiveMode::NonRecursive)?;
+        self.counts.insert(p.to_owned(), 1);
+        Ok(())
+    }
+
+    fn unwatch(&mut self, p: &Path) {
+        if let Some(count) = self.counts.get_mut(p) {
+            *count -= 1;
+            if *count == 0 {
+                self.counts.remove(p);
+                // fails if the file was deleted in the meantime, which is fine
+                let _ = self.watcher.unwatch(p);
+            }
+        }
+    }
 }
 
 impl FileReader {
     fn new(
-        content_sender: Sender<io::Result<String>>,
+        content_sender: Sender<(usize, io::Result<LogUpdate>)>,
+        index: usize,
         receiver: Receiver<()>,
         file_path: PathBuf,
+        remote_command: Option<String>,
         interval: Duration,
+        target: ReadTarget,
     ) -> Self {
         FileReader {
-            content_sender: content_sender,
-            receiver: receiver,
-            file_path: file_path,
-            interval: interval,
-            content: "".to_string(),
+            content_sender,
+            index,
+            receiver,
+            file_path,
+            remote_command,
+            interval,
+            // a merged log was sent to the app when it was created
+            opened: matches!(target, ReadTarget::Merged { .. }),
+            target,
+            pending: Vec::new(),
             pos: 0,
+            head: Vec::new(),
+            inode: None,
+            previous_read: None,
+            first_read: None,
         }
     }
 
     fn run(&mut self) -> Result<(), ()> {
+        if let Some(previous_read) = self.previous_read.take() {
+            // until the previous reader is done with its first read, or has stopped
+            let _ = previous_read.recv();
+        }
         loop {
             self.update().map_err(|_| ())?;
+            self.first_read = None;
             select! {
                 recv(self.receiver) -> msg => {
                     msg.map_err(|_| ())?;
@@ -150,36 +393,369 @@ impl FileReader {
         }
     }
 
-    fn update(&mut self) -> Result<(), SendError<io::Result<String>>> {
-        let s = File::open(&self.file_path).and_then(|mut f| {
-            // avoid reading the whole file every time
-            self.pos = f.seek(io::SeekFrom::Start(self.pos))?;
-            self.pos += f.read_to_string(&mut self.content)? as u64;
-            Ok(self.content.clone())
-        });
-        // let s = fs::read_to_string(&self.file_path); // alternative: always read the whole file
-        self.content_sender.send(s)
+    fn update(&mut self) -> Result<(), SendError<(usize, io::Result<LogUpdate>)>> {
+        let changed = match self.remote_command.clone() {
+            Some(command) => self.read_remote(&command),
+            None => File::open(&self.file_path).and_then(|mut f| self.read_new_content(&mut f)),
+        };
+        let update = match changed {
+            Ok(_) if !self.opened => {
+                self.opened = true;
+                Ok(LogUpdate::Opened(self.target.buffer().clone()))
+            }
+            Ok(Some(from)) => Ok(LogUpdate::Appended(from)),
+            Ok(None) => return Ok(()),
+            // one of several merged files not being readable isn't worth hiding the others for
+            Err(_) if matches!(self.target, ReadTarget::Merged { .. }) => return Ok(()),
+            Err(e) => {
+                self.opened = false;
+                Err(e)
+            }
+        };
+        self.content_sender.send((self.index, update))
+    }
+
+    /// Appends what was written to the file since the last read to the buffer, in chunks so a large
+    /// file is never held in memory twice. Returns the index of the first changed line, if any.
+    fn read_new_content(&mut self, f: &mut File) -> io::Result<Option<usize>> {
+        let mut changed = None;
+        if let Some(reason) = self.check_replaced(f)? {
+            // start over, but keep what was shown so far
+            self.pos = 0;
+            self.pending.clear();
+            self.head.clear();
+            changed = Some(self.target.buffer().write().unwrap().append_marker(reason));
+        }
+
+        // avoid reading the whole file every time
+        self.pos = f.seek(io::SeekFrom::Start(self.pos))?;
+        let mut chunk = vec![0; READ_CHUNK_SIZE];
+        loop {
+            let n = f.read(&mut chunk)?;
+            if n == 0 {
+                return Ok(changed);
+            }
+            if self.head.len() < HEAD_LEN && self.head.len() as u64 == self.pos {
+                self.head
+                    .extend_from_slice(&chunk[..n.min(HEAD_LEN - self.head.len())]);
+            }
+            changed = changed.or(self.append(&chunk[..n]));
+        }
+    }
+
+    /// Appends what the remote command prints to the buffer. A command without `{offset}` prints
+    /// the whole file every time, of which only the new part is used.
+    fn read_remote(&mut self, command: &str) -> io::Result<Option<usize>> {
+        let output = Command::new("sh")
+            .arg("-c")
+            .arg(command.replace("{offset}", &(self.pos + 1).to_string()))
+            .output()?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(io::Error::other(match stderr.trim() {
+                "" => format!("remote command failed ({})", output.status),
+                e => e.to_owned(),
+            }));
+        }
+        let mut new = &output.stdout[..];
+        let mut changed = None;
+        if !command.contains("{offset}") {
+            if (new.len() as u64) < self.pos {
+                self.pos = 0;
+                self.pending.clear();
+                changed = Some(
+                    self.target
+                        .buffer()
+                        .write()
+                        .unwrap()
+                        .append_marker("file truncated"),
+                );
+            }
+            new = &new[self.pos as usize..];
+        }
+        if new.is_empty() {
+            return Ok(changed);
+        }
+        Ok(changed.or(self.append(new)))
+    }
+
+    /// Appends bytes read from the file to the buffer and returns the index of the first changed
+    /// line, if any.
+    fn append(&mut self, bytes: &[u8]) -> Option<usize> {
+        self.pos += bytes.len() as u64;
+        self.pending.extend_from_slice(bytes);
+        let s = decode_lossy(&mut self.pending);
+        match &mut self.target {
+            ReadTarget::Own(buffer) => Some(buffer.write().unwrap().append(&s)),
+            ReadTarget::Merged {
+                buffer,
+                tag,
+                partial,
+            } => buffer.write().unwrap().append_lines(partial, &s, tag),
+        }
     }
 }
 
+impl FileReader {
+    /// Checks whether the file was truncated or replaced by a new one since the last read, e.g.
+    /// because the job was requeued or the log rotated.
+    fn check_replaced(&mut self, f: &mut File) -> io::Result<Option<&'static str>> {
+        let metadata = f.metadata()?;
+        let inode = inode(&metadata);
+        let reason = if self.inode.is_some() && inode != self.inode {
+            Some("file recreated")
+        } else if metadata.len() < self.pos {
+            Some("file truncated")
+        } else if !self.head.is_empty() {
+            // truncated and written again since the last read, or replaced by a file that reuses
+            // the inode
+            let mut head = vec![0; self.head.len()];
+            f.seek(io::SeekFrom::Start(0))?;
+            f.read_exact(&mut head)?;
+            (head != self.head).then_some("file rewritten")
+        } else {
+            None
+        };
+        self.inode = inode;
+        Ok(reason)
+    }
+}
+
+#[cfg(unix)]
+fn inode(metadata: &Metadata) -> Option<u64> {
+    use std::os::unix::fs::MetadataExt;
+    Some(metadata.ino())
+}
+
+#[cfg(not(unix))]
+fn inode(_metadata: &Metadata) -> Option<u64> {
+    None
+}
+
 impl FileWatcherHandle {
-    pub fn new(app: Sender<AppMessage>, interval: Duration) -> Self {
+    pub fn new(app: Sender<AppMessage>, interval: Duration, max_lines: Option<usize>) -> Self {
         let (sender, receiver) = unbounded();
-        let mut actor = FileWatcher::new(app, receiver, interval);
+        let mut actor = FileWatcher::new(app, receiver, interval, max_lines);
         thread::spawn(move || actor.run());
 
         Self {
             sender,
-            file_path: None,
+            files: Vec::new(),
+            merged: false,
+            tile_files: Vec::new(),
         }
     }
 
-    pub fn set_file_path(&mut self, file_path: Option<PathBuf>) {
-        if self.file_path != file_path {
-            self.file_path = file_path.clone();
+    /// Shows these files, each in its own log or merged into one.
+    pub fn set_files(&mut self, files: Vec<LogFile>, merged: bool) {
+        if self.files != files || self.merged != merged {
+            self.files = files.clone();
+            self.merged = merged;
             self.sender
-                .send(FileWatcherMessage::FilePath(file_path))
+                .send(FileWatcherMessage::Files(files, merged))
+                .unwrap();
+        }
+    }
+
+    /// Shows these files in tiles, each in its own log.
+    pub fn set_tile_files(&mut self, tile_files: Vec<LogFile>) {
+        if self.tile_files != tile_files {
+            self.tile_files = tile_files.clone();
+            self.sender
+                .send(FileWatcherMessage::TileFiles(tile_files))
+                .unwrap();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reader(command: &str) -> FileReader {
+        let (content_sender, _) = unbounded();
+        let (_, receiver) = unbounded();
+        let target = ReadTarget::Own(LogBuffer::shared(None));
+        FileReader::new(
+            content_sender,
+            0,
+            receiver,
+            PathBuf::new(),
+            Some(command.to_owned()),
+            Duration::ZERO,
+            target,
+        )
+    }
+
+    fn local_reader(path: &Path) -> FileReader {
+        let (content_sender, _) = unbounded();
+        let (_, receiver) = unbounded();
+        let target = ReadTarget::Own(LogBuffer::shared(None));
+        FileReader::new(
+            content_sender,
+            0,
+            receiver,
+            path.to_owned(),
+            None,
+            Duration::ZERO,
+            target,
+        )
+    }
+
+    fn read_local(reader: &mut FileReader) -> io::Result<Option<usize>> {
+        File::open(reader.file_path.clone()).and_then(|mut f| reader.read_new_content(&mut f))
+    }
+
+    fn read(reader: &mut FileReader) -> io::Result<Option<usize>> {
+        let command = reader.remote_command.clone().unwrap();
+        reader.read_remote(&command)
+    }
+
+    fn lines(reader: &FileReader) -> Vec<String> {
+        let buffer = reader.target.buffer().read().unwrap();
+        buffer.range(0, usize::MAX).map(str::to_owned).collect()
+    }
+
+    #[test]
+    fn test_remote_command() {
+        let command = remote_command(
+            "ssh {node} tail -c +{offset} {path}",
+            "gpu01",
+            "12",
+            Path::new("/tmp/it's.log"),
+        );
+        assert_eq!(
+            command,
+            r#"ssh 'gpu01' tail -c +{offset} '/tmp/it'\''s.log'"#
+        );
+    }
+
+    #[test]
+    fn test_read_remote() {
+        let path =
+            std::env::temp_dir().join(format!("turf-test-remote-{}.log", std::process::id()));
+        std::fs::write(&path, "a\nb").unwrap();
+        let path_arg = path.to_string_lossy();
+        let mut tail = reader(&remote_command(
+            "tail -c +{offset} {path}",
+            "node",
+            "1",
+            &path,
+        ));
+        let mut cat = reader(&remote_command("cat {path}", "node", "1", &path));
+        for reader in [&mut tail, &mut cat] {
+            assert_eq!(read(reader).unwrap(), Some(0));
+        }
+
+        std::fs::write(&path, "a\nbc\nd\n").unwrap();
+        for reader in [&mut tail, &mut cat] {
+            assert_eq!(read(reader).unwrap(), Some(1));
+            assert_eq!(read(reader).unwrap(), None);
+            assert_eq!(lines(reader), vec!["a", "bc", "d"]);
+            assert_eq!(reader.pos, 7);
+        }
+
+        std::fs::write(&path, "e\n").unwrap();
+        assert_eq!(read(&mut cat).unwrap(), Some(3));
+        assert_eq!(
+            lines(&cat)[3..],
+            ["\x1b[7m── file truncated ──\x1b[0m", "e"]
+        );
+
+        std::fs::remove_file(&path).unwrap();
+        let e = read(&mut tail).unwrap_err();
+        assert!(e.to_string().contains(&*path_arg), "{}", e);
+    }
+
+    #[test]
+    fn test_replaced_file() {
+        let path =
+            std::env::temp_dir().join(format!("turf-test-replaced-{}.log", std::process::id()));
+        let marker = |reason: &str| format!("\x1b[7m── {} ──\x1b[0m", reason);
+        fn truncate(path: &Path) {
+            std::fs::write(path, "c\n").unwrap();
+        }
+        fn recreate(path: &Path) {
+            // a new file moved over the old one, as when a log is rotated; both exist at once, so
+            // the inode can't be reused
+            let new = path.with_extension("new");
+            std::fs::write(&new, "aaaa\nbbbb\ncc\n").unwrap();
+            std::fs::rename(&new, path).unwrap();
+        }
+        fn rewrite(path: &Path) {
+            std::fs::write(path, "cccc\ndddd\n").unwrap();
+        }
+        let cases = [
+            ("file truncated", truncate as fn(&Path)),
+            ("file recreated", recreate),
+            ("file rewritten", rewrite),
+        ];
+        for (reason, replace) in cases {
+            std::fs::write(&path, "aaaa\nbbbb\n").unwrap();
+            let mut reader = local_reader(&path);
+            assert_eq!(read_local(&mut reader).unwrap(), Some(0));
+            assert_eq!(read_local(&mut reader).unwrap(), None, "{}", reason);
+
+            replace(&path);
+            assert_eq!(read_local(&mut reader).unwrap(), Some(2), "{}", reason);
+            let content = std::fs::read_to_string(&path).unwrap();
+            // read again from the start of the new file
+            assert_eq!(reader.pos, content.len() as u64, "{}", reason);
+            let mut expected = vec!["aaaa".to_owned(), "bbbb".to_owned(), marker(reason)];
+            expected.extend(content.lines().map(str::to_owned));
+            assert_eq!(lines(&reader), expected);
+            assert_eq!(read_local(&mut reader).unwrap(), None, "{}", reason);
+        }
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_merged_order() {
+        let dir = std::env::temp_dir();
+        let out = dir.join(format!("turf-test-merged-{}.out", std::process::id()));
+        let err = dir.join(format!("turf-test-merged-{}.err", std::process::id()));
+        std::fs::write(&out, "a\nb\n").unwrap();
+        std::fs::write(&err, "c\n").unwrap();
+        // stdout is read last, but still comes first
+        let files = vec![
+            LogFile {
+                path: out.clone(),
+                remote_command: Some(remote_command("sleep 0.2; cat {path}", "", "", &out)),
+            },
+            LogFile {
+                path: err.clone(),
+                remote_command: Some(remote_command("cat {path}", "", "", &err)),
+            },
+        ];
+        let (app, _) = unbounded();
+        let (_, receiver) = unbounded();
+        let file_watcher = FileWatcher::new(app, receiver, Duration::from_secs(3600), None);
+        let mut watches = Watches {
+            watcher: notify::recommended_watcher(|_| {}).unwrap(),
+            counts: HashMap::new(),
+        };
+        let mut group = ReaderGroup::new();
+        file_watcher.open(&mut group, &mut watches, files, true, AppMessage::JobOutput);
+        let Ok((0, Ok(LogUpdate::Opened(buffer)))) = group.content_receiver.recv() else {
+            panic!("no merged log");
+        };
+        // each reader's first read
+        for _ in 0..2 {
+            group
+                .content_receiver
+                .recv_timeout(Duration::from_secs(5))
+                .unwrap()
+                .1
                 .unwrap();
         }
+        let lines: Vec<_> = buffer
+            .read()
+            .unwrap()
+            .range(0, usize::MAX)
+            .map(str::to_owned)
+            .collect();
+        assert_eq!(lines, ["a", "b", format!("{}c", MERGED_TAG).as_str()]);
+        std::fs::remove_file(&out).unwrap();
+        std::fs::remove_file(&err).unwrap();
     }
 }
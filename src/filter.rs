@@ -0,0 +1,431 @@
+use std::fmt;
+
+use regex::{Regex, RegexBuilder};
+
+use crate::app::Job;
+use crate::config::SavedFilters;
+use crate::grouping::{parse_mem_mb, Resources};
+
+/// A persistent job filter such as `state:R partition:gpu name~^train time>2h`.
+///
+/// Terms are separated by whitespace and must all match:
+/// - `field:a,b` matches if the field equals one of the values (case-insensitive),
+/// - `field~regex` matches the field against a regular expression,
+/// - `field>v`, `field>=v`, `field<v`, `field<=v` compare `time`, `cpus`, `gpus` and `mem`,
+/// - a bare word matches job ids and names containing it,
+/// - `@name` expands to the saved filter `name`,
+/// - a leading `!` negates a term.
+pub struct Filter {
+    source: String,
+    terms: Vec<Term>,
+}
+
+#[derive(Debug)]
+pub struct FilterError(String);
+
+impl fmt::Display for FilterError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+struct Term {
+    negate: bool,
+    test: Test,
+}
+
+enum Test {
+    Contains(String),
+    Equals(TextField, Vec<String>),
+    Matches(TextField, Regex),
+    Compare(NumField, CmpOp, u64),
+}
+
+#[derive(Clone, Copy)]
+enum TextField {
+    Id,
+    Name,
+    User,
+    Account,
+    Partition,
+    State,
+    Reason,
+    Nodes,
+}
+
+#[derive(Clone, Copy)]
+enum NumField {
+    Time,
+    Cpus,
+    Gpus,
+    Mem,
+}
+
+#[derive(Clone, Copy)]
+enum CmpOp {
+    Lt,
+    Le,
+    Eq,
+    Ge,
+    Gt,
+}
+
+enum Field {
+    Text(TextField),
+    Num(NumField),
+}
+
+impl Field {
+    fn parse(s: &str) -> Option<Self> {
+        Some(match s.to_ascii_lowercase().as_str() {
+            "id" | "jobid" => Field::Text(TextField::Id),
+            "name" => Field::Text(TextField::Name),
+            "user" => Field::Text(TextField::User),
+            "account" => Field::Text(TextField::Account),
+            "partition" | "part" => Field::Text(TextField::Partition),
+            "state" | "st" => Field::Text(TextField::State),
+            "reason" => Field::Text(TextField::Reason),
+            "node" | "nodes" | "nodelist" => Field::Text(TextField::Nodes),
+            "time" => Field::Num(NumField::Time),
+            "cpu" | "cpus" => Field::Num(NumField::Cpus),
+            "gpu" | "gpus" => Field::Num(NumField::Gpus),
+            "mem" => Field::Num(NumField::Mem),
+            _ => return None,
+        })
+    }
+}
+
+impl Filter {
+    pub fn parse(source: &str, saved: &SavedFilters) -> Result<Self, FilterError> {
+        let mut terms = Vec::new();
+        parse_terms(source, saved, 0, &mut terms)?;
+        Ok(Filter {
+            source: source.trim().to_owned(),
+            terms,
+        })
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    pub fn matches(&self, job: &Job) -> bool {
+        self.terms.iter().all(|t| t.test.matches(job) != t.negate)
+    }
+}
+
+fn parse_terms(
+    source: &str,
+    saved: &SavedFilters,
+    depth: usize,
+    terms: &mut Vec<Term>,
+) -> Result<(), FilterError> {
+    if depth > 8 {
+        return Err(FilterError(
+            "saved filters reference each other recursively".to_owned(),
+        ));
+    }
+
+    for token in tokenize(source)? {
+        let (negate, token) = match token.strip_prefix('!') {
+            Some(rest) => (true, rest.to_owned()),
+            None => (false, token),
+        };
+
+        if let Some(name) = token.strip_prefix('@') {
+            let expr = saved
+                .get(name)
+                .ok_or_else(|| FilterError(format!("unknown saved filter '@{}'", name)))?;
+            if negate {
+                return Err(FilterError(format!(
+                    "saved filter '@{}' cannot be negated",
+                    name
+                )));
+            }
+            parse_terms(expr, saved, depth + 1, terms)?;
+            continue;
+        }
+
+        terms.push(Term {
+            negate,
+            test: parse_test(&token)?,
+        });
+    }
+    Ok(())
+}
+
+/// Splits on whitespace, keeping double-quoted sections (e.g. `name:"my job"`) together.
+fn tokenize(source: &str) -> Result<Vec<String>, FilterError> {
+    let mut tokens = Vec::new();
+    let mut token = String::new();
+    let mut in_quotes = false;
+    for c in source.chars() {
+        match c {
+            '"' => in_quotes = !in_quotes,
+            c if c.is_whitespace() && !in_quotes => {
+                if !token.is_empty() {
+                    tokens.push(std::mem::take(&mut token));
+                }
+            }
+            c => token.push(c),
+        }
+    }
+    if in_quotes {
+        return Err(FilterError("unterminated quote".to_owned()));
+    }
+    if !token.is_empty() {
+        tokens.push(token);
+    }
+    Ok(tokens)
+}
+
+fn parse_test(token: &str) -> Result<Test, FilterError> {
+    let Some(op_start) = token.find([':', '~', '<', '>', '=']) else {
+        return Ok(Test::Contains(token.to_lowercase()));
+    };
+    let (field, rest) = token.split_at(op_start);
+    let (op, value) = ["<=", ">=", ":", "=", "~", "<", ">"]
+        .iter()
+        .find_map(|op| rest.strip_prefix(op).map(|v| (*op, v)))
+        .unwrap();
+
+    let field =
+        Field::parse(field).ok_or_else(|| FilterError(format!("unknown field '{}'", field)))?;
+    if value.is_empty() {
+        return Err(FilterError(format!("missing value in '{}'", token)));
+    }
+
+    match (field, op) {
+        (Field::Text(f), ":" | "=") => Ok(Test::Equals(
+            f,
+            value.split(',').map(|v| v.to_lowercase()).collect(),
+        )),
+        (Field::Text(f), "~") => RegexBuilder::new(value)
+            .case_insensitive(true)
+            .build()
+            .map(|re| Test::Matches(f, re))
+            .map_err(|e| FilterError(format!("invalid regex '{}': {}", value, e))),
+        (Field::Num(f), op) if op != "~" => {
+            let op = match op {
+                "<" => CmpOp::Lt,
+                "<=" => CmpOp::Le,
+                ">=" => CmpOp::Ge,
+                ">" => CmpOp::Gt,
+                _ => CmpOp::Eq,
+            };
+            let value = match f {
+                NumField::Time => parse_duration(value),
+                NumField::Mem => parse_mem_mb(value).or_else(|| value.parse().ok()),
+                NumField::Cpus | NumField::Gpus => value.parse().ok(),
+            }
+            .ok_or_else(|| FilterError(format!("invalid value in '{}'", token)))?;
+            Ok(Test::Compare(f, op, value))
+        }
+        _ => Err(FilterError(format!(
+            "operator '{}' is not supported in '{}'",
+            op, token
+        ))),
+    }
+}
+
+impl Test {
+    fn matches(&self, job: &Job) -> bool {
+        match self {
+            Test::Contains(s) => {
+                job.id().to_lowercase().contains(s) || job.name.to_lowercase().contains(s)
+            }
+            Test::Equals(f, values) => f
+                .values(job)
+                .iter()
+                .any(|v| values.iter().any(|value| v.eq_ignore_ascii_case(value))),
+            Test::Matches(f, re) => f.values(job).iter().any(|v| re.is_match(v)),
+            Test::Compare(f, op, value) => {
+                let Some(actual) = f.value(job) else {
+                    return false;
+                };
+                match op {
+                    CmpOp::Lt => actual < *value,
+                    CmpOp::Le => actual <= *value,
+                    CmpOp::Eq => actual == *value,
+                    CmpOp::Ge => actual >= *value,
+                    CmpOp::Gt => actual > *value,
+                }
+            }
+        }
+    }
+}
+
+impl TextField {
+    fn values(self, job: &Job) -> Vec<String> {
+        match self {
+            TextField::Id => vec![job.id(), job.job_id.clone(), job.array_id.clone()],
+            TextField::Name => vec![job.name.clone()],
+            TextField::User => vec![job.user.clone()],
+            TextField::Account => vec![job.account.clone()],
+            TextField::Partition => vec![job.partition.clone()],
+            TextField::State => vec![job.state.clone(), job.state_compact.clone()],
+            TextField::Reason => vec![job.reason.clone().unwrap_or_default()],
+            TextField::Nodes => vec![job.nodelist.clone()],
+        }
+    }
+}
+
+impl NumField {
+    fn value(self, job: &Job) -> Option<u64> {
+        match self {
+            NumField::Time => parse_slurm_time(&job.time),
+            NumField::Cpus => Some(Resources::from_tres(&job.tres).cpus),
+            NumField::Gpus => Some(Resources::from_tres(&job.tres).gpus),
+            NumField::Mem => Some(Resources::from_tres(&job.tres).mem_mb),
+        }
+    }
+}
+
+/// Parses Slurm's elapsed time format (`[days-]hours:minutes:seconds` or `minutes:seconds`) into seconds.
+pub fn parse_slurm_time(s: &str) -> Option<u64> {
+    let (days, hms) = match s.split_once('-') {
+        Some((d, hms)) => (d.parse::<u64>().ok()?, hms),
+        None => (0, s),
+    };
+    let parts = hms
+        .split(':')
+        .map(|p| p.parse::<u64>().ok())
+        .collect::<Option<Vec<_>>>()?;
+    // `None` for times too long to count in seconds
+    let seconds = |h: u64, m: u64, s: u64| {
+        h.checked_mul(3600)?
+            .checked_add(m.checked_mul(60)?)?
+            .checked_add(s)
+    };
+    let seconds = match parts[..] {
+        [h, m, s] => seconds(h, m, s)?,
+        [m, s] => seconds(0, m, s)?,
+        [m] if days == 0 => seconds(0, m, 0)?,
+        [h] => seconds(h, 0, 0)?,
+        _ => return None,
+    };
+    days.checked_mul(86400)?.checked_add(seconds)
+}
+
+/// Parses durations like `2h`, `1h30m`, `45s`, `1d` or Slurm's `1-00:00:00` into seconds.
+/// A plain number is taken as minutes, like `sbatch --time`.
+fn parse_duration(s: &str) -> Option<u64> {
+    if s.contains(':') || s.contains('-') || s.chars().all(|c| c.is_ascii_digit()) {
+        return parse_slurm_time(s);
+    }
+
+    let mut total: u64 = 0;
+    let mut number = String::new();
+    for c in s.chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+        let unit = match c {
+            's' => 1,
+            'm' => 60,
+            'h' => 3600,
+            'd' => 86400,
+            _ => return None,
+        };
+        total = total.checked_add(number.parse::<u64>().ok()?.checked_mul(unit)?)?;
+        number.clear();
+    }
+    number.is_empty().then_some(total)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn job(
+        id: &str,
+        name: &str,
+        state: &str,
+        state_compact: &str,
+        partition: &str,
+        time: &str,
+    ) -> Job {
+        Job {
+            job_id: id.to_owned(),
+            array_id: id.to_owned(),
+            name: name.to_owned(),
+            state: state.to_owned(),
+            state_compact: state_compact.to_owned(),
+            user: "alice".to_owned(),
+            account: "proj".to_owned(),
+            time: time.to_owned(),
+            tres: "cpu=8,mem=32G,node=1,gres/gpu=2".to_owned(),
+            partition: partition.to_owned(),
+            nodelist: "node01".to_owned(),
+            ..Job::default()
+        }
+    }
+
+    fn matches(filter: &str, job: &Job) -> bool {
+        Filter::parse(filter, &SavedFilters::new())
+            .unwrap()
+            .matches(job)
+    }
+
+    #[test]
+    fn test_filter_terms() {
+        let j = job("42", "train_resnet", "RUNNING", "R", "gpu", "2:30:00");
+
+        assert!(matches("", &j));
+        assert!(matches("state:R partition:gpu", &j));
+        assert!(matches("state:running", &j));
+        assert!(matches("state:PD,R", &j));
+        assert!(!matches("state:PD", &j));
+        assert!(matches("!state:PD", &j));
+        assert!(matches("name~^train", &j));
+        assert!(!matches("name~^eval", &j));
+        assert!(matches("time>2h", &j));
+        assert!(!matches("time>=3h", &j));
+        assert!(matches("time<1d", &j));
+        assert!(matches("gpus>=2 cpus:8 mem>16G", &j));
+        assert!(matches("resnet", &j));
+        assert!(matches("id:42", &j));
+        assert!(matches("name:\"train_resnet\"", &j));
+    }
+
+    #[test]
+    fn test_filter_saved() {
+        let j = job("42", "train_resnet", "PENDING", "PD", "gpu", "0:00");
+        let saved = SavedFilters::from([("gpu".to_owned(), "partition:gpu".to_owned())]);
+
+        assert!(Filter::parse("@gpu state:PD", &saved).unwrap().matches(&j));
+        assert!(Filter::parse("@missing", &saved).is_err());
+    }
+
+    #[test]
+    fn test_filter_errors() {
+        let saved = SavedFilters::new();
+        assert!(Filter::parse("foo:bar", &saved).is_err());
+        assert!(Filter::parse("name~(", &saved).is_err());
+        assert!(Filter::parse("time>2x", &saved).is_err());
+        assert!(Filter::parse("state>R", &saved).is_err());
+        assert!(Filter::parse("time~1", &saved).is_err());
+        assert!(Filter::parse("name:", &saved).is_err());
+        assert!(Filter::parse("name:\"open", &saved).is_err());
+    }
+
+    #[test]
+    fn test_parse_durations() {
+        assert_eq!(parse_slurm_time("1-02:03:04"), Some(93784));
+        assert_eq!(parse_slurm_time("02:03:04"), Some(7384));
+        assert_eq!(parse_slurm_time("3:04"), Some(184));
+        assert_eq!(parse_slurm_time("INVALID"), None);
+        assert_eq!(parse_duration("1h30m"), Some(5400));
+        assert_eq!(parse_duration("90"), Some(5400));
+        assert_eq!(parse_duration("1d"), Some(86400));
+        assert_eq!(parse_duration("2x"), None);
+        // too long to count in seconds
+        assert_eq!(parse_duration("99999999999999999d"), None);
+        assert_eq!(parse_duration("213503982334601d1d"), None);
+        assert_eq!(parse_duration("999999999999999999"), None);
+        assert_eq!(parse_slurm_time("99999999999999999-00:00:00"), None);
+        assert_eq!(parse_slurm_time("1:18446744073709551615"), None);
+        assert!(Filter::parse("time>99999999999999999d", &SavedFilters::new()).is_err());
+    }
+}
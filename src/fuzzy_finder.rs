@@ -0,0 +1,279 @@
+use fuzzy_matcher::{skim::SkimMatcherV2, FuzzyMatcher};
+use regex::{Regex, RegexBuilder};
+
+use crate::app::{DisplayJob, Job};
+
+/// How the fuzzy finder query is matched against the job table.
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+pub enum MatchMode {
+    #[default]
+    Fuzzy,
+    Substring,
+    Regex,
+}
+
+impl MatchMode {
+    pub fn next(self) -> Self {
+        match self {
+            MatchMode::Fuzzy => MatchMode::Substring,
+            MatchMode::Substring => MatchMode::Regex,
+            MatchMode::Regex => MatchMode::Fuzzy,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            MatchMode::Fuzzy => "fuzzy",
+            MatchMode::Substring => "substring",
+            MatchMode::Regex => "regex",
+        }
+    }
+}
+
+/// A table column that is searched and can show match highlights.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Column {
+    State,
+    Id,
+    Partition,
+    User,
+    Name,
+    Account,
+}
+
+/// Char indices of the matched characters, per column.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Highlights(Vec<(Column, Vec<usize>)>);
+
+impl Highlights {
+    pub fn get(&self, column: Column) -> &[usize] {
+        self.0
+            .iter()
+            .find(|(c, _)| *c == column)
+            .map_or(&[], |(_, indices)| indices.as_slice())
+    }
+}
+
+pub enum Matcher {
+    Fuzzy(Box<SkimMatcherV2>, String),
+    Substring(String, bool), // (needle, case_sensitive)
+    Regex(Regex),
+}
+
+impl Matcher {
+    /// Builds a matcher for the query. All modes are smart case: case-insensitive unless the
+    /// query contains an uppercase letter.
+    pub fn new(query: &str, mode: MatchMode) -> Result<Self, String> {
+        let case_sensitive = query.chars().any(|c| c.is_uppercase());
+        match mode {
+            MatchMode::Fuzzy => Ok(Matcher::Fuzzy(
+                Box::new(SkimMatcherV2::default().smart_case()),
+                query.to_owned(),
+            )),
+            MatchMode::Substring => Ok(Matcher::Substring(query.to_owned(), case_sensitive)),
+            MatchMode::Regex => RegexBuilder::new(query)
+                .case_insensitive(!case_sensitive)
+                .build()
+                .map(Matcher::Regex)
+                .map_err(|e| e.to_string()),
+        }
+    }
+
+    /// Matches the query against the given columns and returns what to highlight, or `None` if the
+    /// row doesn't match.
+    pub fn find(&self, columns: &[(Column, &str)]) -> Option<Highlights> {
+        match self {
+            Matcher::Fuzzy(matcher, query) => {
+                // match against all columns at once, so a query can span e.g. user and name
+                let text = columns
+                    .iter()
+                    .map(|(_, s)| *s)
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                let (_, indices) = matcher.fuzzy_indices(&text, query)?;
+
+                let mut highlights = Vec::new();
+                let mut start = 0;
+                for (column, s) in columns {
+                    let len = s.chars().count();
+                    let column_indices: Vec<usize> = indices
+                        .iter()
+                        .filter(|&&i| i >= start && i < start + len)
+                        .map(|i| i - start)
+                        .collect();
+                    if !column_indices.is_empty() {
+                        highlights.push((*column, column_indices));
+                    }
+                    start += len + 1;
+                }
+                Some(Highlights(highlights))
+            }
+            Matcher::Substring(needle, case_sensitive) => {
+                let needle: Vec<char> = needle.chars().collect();
+                let eq = |a: char, b: char| {
+                    if *case_sensitive {
+                        a == b
+                    } else {
+                        a.to_lowercase().eq(b.to_lowercase())
+                    }
+                };
+                Self::collect(columns, |s| {
+                    let chars: Vec<char> = s.chars().collect();
+                    let mut indices = Vec::new();
+                    let mut i = 0;
+                    while !needle.is_empty() && i + needle.len() <= chars.len() {
+                        if chars[i..i + needle.len()]
+                            .iter()
+                            .zip(&needle)
+                            .all(|(&a, &b)| eq(a, b))
+                        {
+                            indices.extend(i..i + needle.len());
+                            i += needle.len();
+                        } else {
+                            i += 1;
+                        }
+                    }
+                    (!indices.is_empty()).then_some(indices)
+                })
+            }
+            // a match can be empty, e.g. for `^`, which matches without anything to highlight
+            Matcher::Regex(re) => Self::collect(columns, |s| {
+                re.is_match(s).then(|| {
+                    re.find_iter(s)
+                        .flat_map(|m| {
+                            let start = s[..m.start()].chars().count();
+                            start..start + m.as_str().chars().count()
+                        })
+                        .collect()
+                })
+            }),
+        }
+    }
+
+    /// Highlights of the columns `find` matches, which returns the indices to highlight, or `None`
+    /// for a column that doesn't match.
+    fn collect(
+        columns: &[(Column, &str)],
+        find: impl Fn(&str) -> Option<Vec<usize>>,
+    ) -> Option<Highlights> {
+        let matches: Vec<_> = columns
+            .iter()
+            .filter_map(|(column, s)| Some((*column, find(s)?)))
+            .collect();
+        if matches.is_empty() {
+            return None;
+        }
+        Some(Highlights(
+            matches
+                .into_iter()
+                .filter(|(_, indices)| !indices.is_empty())
+                .collect(),
+        ))
+    }
+}
+
+/// The columns of a job that are searched, with the id of the job itself rather than of its row.
+fn job_columns(job: &Job) -> [(Column, String); 6] {
+    [
+        (Column::State, job.state_compact.clone()),
+        (Column::Id, job.id()),
+        (Column::Partition, job.partition.clone()),
+        (Column::User, job.user.clone()),
+        (Column::Name, job.name.clone()),
+        (Column::Account, job.account.clone()), // searched, but not shown
+    ]
+}
+
+/// The jobs matching `matcher`.
+pub fn narrow<'a>(jobs: &[&'a Job], matcher: &Matcher) -> Vec<&'a Job> {
+    jobs.iter()
+        .copied()
+        .filter(|job| {
+            let columns = job_columns(job);
+            let columns = columns.each_ref().map(|(c, s)| (*c, s.as_str()));
+            matcher.find(&columns).is_some()
+        })
+        .collect()
+}
+
+/// Sets the highlights of the job rows for what `matcher` matches in them.
+pub fn highlight(rows: &mut [DisplayJob], matcher: &Matcher) {
+    for row in rows.iter_mut().filter(|r| r.group.is_none()) {
+        let id = row.display_id();
+        let columns = [
+            (Column::State, row.state_compact.as_str()),
+            (Column::Id, id.as_str()),
+            (Column::Partition, row.partition.as_str()),
+            (Column::User, row.user.as_str()),
+            (Column::Name, row.name.as_str()),
+        ];
+        // a collapsed array row may only match through one of its tasks' ids
+        row.highlights = matcher.find(&columns).unwrap_or_default();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ROW: [(Column, &str); 3] = [
+        (Column::Id, "1234"),
+        (Column::User, "alice"),
+        (Column::Name, "Train_ResNet"),
+    ];
+
+    #[test]
+    fn test_substring_highlights() {
+        let m = Matcher::new("res", MatchMode::Substring).unwrap();
+        let h = m.find(&ROW).unwrap();
+        assert_eq!(h.get(Column::Name), &[6, 7, 8]);
+        assert!(h.get(Column::User).is_empty());
+
+        // smart case
+        assert!(Matcher::new("Res", MatchMode::Substring)
+            .unwrap()
+            .find(&ROW)
+            .is_some());
+        assert!(Matcher::new("RES", MatchMode::Substring)
+            .unwrap()
+            .find(&ROW)
+            .is_none());
+    }
+
+    #[test]
+    fn test_regex_highlights() {
+        let m = Matcher::new("^a|net$", MatchMode::Regex).unwrap();
+        let h = m.find(&ROW).unwrap();
+        assert_eq!(h.get(Column::User), &[0]);
+        assert_eq!(h.get(Column::Name), &[9, 10, 11]);
+        assert!(Matcher::new("(", MatchMode::Regex).is_err());
+    }
+
+    #[test]
+    fn test_empty_regex_match() {
+        for query in ["^", "x*"] {
+            let h = Matcher::new(query, MatchMode::Regex).unwrap().find(&ROW);
+            assert_eq!(h, Some(Highlights::default()), "{}", query);
+        }
+        let jobs = [Job {
+            name: "train".to_owned(),
+            ..Job::default()
+        }];
+        let jobs: Vec<&Job> = jobs.iter().collect();
+        assert_eq!(
+            narrow(&jobs, &Matcher::new("^", MatchMode::Regex).unwrap()).len(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_fuzzy_highlights_span_columns() {
+        let m = Matcher::new("altr", MatchMode::Fuzzy).unwrap();
+        let h = m.find(&ROW).unwrap();
+        assert_eq!(h.get(Column::User).len() + h.get(Column::Name).len(), 4);
+        assert!(Matcher::new("zzz", MatchMode::Fuzzy)
+            .unwrap()
+            .find(&ROW)
+            .is_none());
+    }
+}
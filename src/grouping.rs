@@ -0,0 +1,434 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+
+use clap::ValueEnum;
+
+use crate::app::{DisplayJob, Job};
+
+/// A job attribute the job list can be grouped by.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum GroupKey {
+    User,
+    Partition,
+    Account,
+    Name,
+    State,
+}
+
+impl GroupKey {
+    pub const ALL: [GroupKey; 5] = [
+        GroupKey::User,
+        GroupKey::Partition,
+        GroupKey::Account,
+        GroupKey::Name,
+        GroupKey::State,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            GroupKey::User => "user",
+            GroupKey::Partition => "partition",
+            GroupKey::Account => "account",
+            GroupKey::Name => "name",
+            GroupKey::State => "state",
+        }
+    }
+
+    /// The key that picks it in the group-by dialog.
+    pub fn key(&self) -> char {
+        match self {
+            GroupKey::User => 'u',
+            GroupKey::Partition => 'p',
+            GroupKey::Account => 'a',
+            GroupKey::Name => 'n',
+            GroupKey::State => 's',
+        }
+    }
+
+    fn value(&self, job: &Job) -> String {
+        match self {
+            GroupKey::User => job.user.clone(),
+            GroupKey::Partition => job.partition.clone(),
+            GroupKey::Account => job.account.clone(),
+            GroupKey::Name => name_prefix(&job.name).to_owned(),
+            GroupKey::State => job.state.clone(),
+        }
+    }
+}
+
+/// Summary of a group header row in the job list.
+#[derive(Clone)]
+pub struct GroupInfo {
+    pub key: GroupKey,
+    pub value: String,
+    /// Unique path of the group, e.g. `user=alice/partition=gpu`.
+    pub path: String,
+    pub collapsed: bool,
+    /// Number of jobs in the group, counting every array task.
+    pub job_count: usize,
+    /// Job counts per compact state (R, PD, CG, ...).
+    pub states: BTreeMap<String, usize>,
+    pub resources: Resources,
+}
+
+/// Resources allocated to one or more jobs, as reported by `tres-alloc`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Resources {
+    pub cpus: u64,
+    pub mem_mb: u64,
+    pub gpus: u64,
+}
+
+impl Resources {
+    /// Parses a TRES string such as `cpu=4,mem=16G,node=1,billing=4,gres/gpu=1`.
+    pub fn from_tres(tres: &str) -> Self {
+        let mut resources = Resources::default();
+        for (key, value) in tres.split(',').filter_map(|kv| kv.split_once('=')) {
+            match key {
+                "cpu" => resources.cpus += value.parse().unwrap_or(0),
+                "mem" => resources.mem_mb += parse_mem_mb(value).unwrap_or(0),
+                "gres/gpu" => resources.gpus += value.parse().unwrap_or(0),
+                _ => {}
+            }
+        }
+        resources
+    }
+
+    fn add(&mut self, other: Resources) {
+        self.cpus += other.cpus;
+        self.mem_mb += other.mem_mb;
+        self.gpus += other.gpus;
+    }
+}
+
+impl fmt::Display for Resources {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} cpu, {} mem", self.cpus, format_mem(self.mem_mb))?;
+        if self.gpus > 0 {
+            write!(f, ", {} gpu", self.gpus)?;
+        }
+        Ok(())
+    }
+}
+
+pub fn parse_mem_mb(value: &str) -> Option<u64> {
+    let (number, unit) = value.split_at(value.find(|c: char| c.is_ascii_alphabetic())?);
+    let number: f64 = number.parse().ok()?;
+    let factor = match unit {
+        "K" => 1.0 / 1024.0,
+        "M" => 1.0,
+        "G" => 1024.0,
+        "T" => 1024.0 * 1024.0,
+        _ => return None,
+    };
+    Some((number * factor).round() as u64)
+}
+
+fn format_mem(mem_mb: u64) -> String {
+    if mem_mb >= 1024 * 1024 {
+        format!("{:.1}T", mem_mb as f64 / (1024.0 * 1024.0))
+    } else if mem_mb >= 1024 {
+        format!("{:.1}G", mem_mb as f64 / 1024.0)
+    } else {
+        format!("{}M", mem_mb)
+    }
+}
+
+/// The part of a job name before the first separator or digit, e.g. `train` for `train_lr3e-4`.
+pub fn name_prefix(name: &str) -> &str {
+    let end = name
+        .find(|c: char| matches!(c, '_' | '-' | '.' | ' ') || c.is_ascii_digit())
+        .unwrap_or(name.len());
+    if end == 0 {
+        name
+    } else {
+        &name[..end]
+    }
+}
+
+/// Builds the job list rows, nesting jobs under one header row per distinct value of each key.
+///
+/// Array tasks inside a group are collapsed into a single row, exactly like the ungrouped list.
+pub fn group_jobs(
+    jobs: &[&Job],
+    keys: &[GroupKey],
+    collapsed: &HashSet<String>,
+) -> Vec<DisplayJob> {
+    let mut display_jobs = Vec::new();
+    push_groups(jobs, keys, collapsed, "", 0, &mut display_jobs);
+    display_jobs
+}
+
+fn push_groups(
+    jobs: &[&Job],
+    keys: &[GroupKey],
+    collapsed: &HashSet<String>,
+    parent_path: &str,
+    depth: usize,
+    display_jobs: &mut Vec<DisplayJob>,
+) {
+    let Some((key, rest)) = keys.split_first() else {
+        display_jobs.extend(collapse_arrays(jobs).into_iter().map(|mut j| {
+            j.depth = depth;
+            j
+        }));
+        return;
+    };
+
+    let mut groups: BTreeMap<String, Vec<&Job>> = BTreeMap::new();
+    for job in jobs {
+        groups.entry(key.value(job)).or_default().push(job);
+    }
+
+    for (value, jobs) in groups {
+        let path = if parent_path.is_empty() {
+            format!("{}={}", key.label(), value)
+        } else {
+            format!("{}/{}={}", parent_path, key.label(), value)
+        };
+        let is_collapsed = collapsed.contains(&path);
+
+        let mut states = BTreeMap::new();
+        let mut resources = Resources::default();
+        for job in &jobs {
+            *states.entry(job.state_compact.clone()).or_default() += 1;
+            resources.add(Resources::from_tres(&job.tres));
+        }
+
+        display_jobs.push(DisplayJob::group(
+            GroupInfo {
+                key: *key,
+                value,
+                path: path.clone(),
+                collapsed: is_collapsed,
+                job_count: jobs.len(),
+                states,
+                resources,
+            },
+            depth,
+        ));
+
+        if !is_collapsed {
+            push_groups(&jobs, rest, collapsed, &path, depth + 1, display_jobs);
+        }
+    }
+}
+
+/// Collapses the tasks of each array job into a single row; other jobs are kept as they are.
+pub fn collapse_arrays(jobs: &[&Job]) -> Vec<DisplayJob> {
+    let mut array_jobs: HashMap<String, Vec<&Job>> = HashMap::new();
+    let mut individual_jobs = Vec::new();
+
+    // Group jobs by array_id
+    for job in jobs {
+        if job.array_step.is_some() {
+            array_jobs
+                .entry(job.array_id.clone())
+                .or_default()
+                .push(job);
+        } else {
+            individual_jobs.push(job);
+        }
+    }
+
+    let mut display_jobs: Vec<DisplayJob> = individual_jobs
+        .into_iter()
+        .map(|job| DisplayJob::from_job(job, job.job_id.clone()))
+        .collect();
+
+    // Add collapsed array jobs
+    for (array_id, jobs) in array_jobs {
+        if let Some(first_job) = jobs.first() {
+            let mut display_job = DisplayJob::from_job(first_job, array_id);
+            display_job.is_array = true;
+            display_job.task_count = Some(jobs.len());
+            display_jobs.push(display_job);
+        }
+    }
+
+    display_jobs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn job(id: &str, user: &str, partition: &str, state: &str, tres: &str) -> Job {
+        Job {
+            job_id: id.to_owned(),
+            array_id: id.to_owned(),
+            user: user.to_owned(),
+            partition: partition.to_owned(),
+            state_compact: state.to_owned(),
+            tres: tres.to_owned(),
+            ..Job::default()
+        }
+    }
+
+    fn task(array_id: &str, step: &str, user: &str, partition: &str) -> Job {
+        Job {
+            job_id: format!("{}_{}", array_id, step),
+            array_id: array_id.to_owned(),
+            array_step: Some(step.to_owned()),
+            ..job("", user, partition, "PD", "")
+        }
+    }
+
+    /// The rows as `depth:id`, with the path and job count for group headers.
+    fn rows(rows: &[DisplayJob]) -> Vec<String> {
+        rows.iter()
+            .map(|r| match &r.group {
+                Some(group) => format!("{}:{} ({})", r.depth, group.path, group.job_count),
+                None => format!("{}:{}", r.depth, r.id()),
+            })
+            .collect()
+    }
+
+    fn test_jobs() -> Vec<Job> {
+        vec![
+            job("1", "bob", "gpu", "R", "cpu=4,mem=16G,gres/gpu=1"),
+            job("2", "alice", "gpu", "R", "cpu=8,mem=32G,gres/gpu=2"),
+            job("3", "alice", "cpu", "PD", ""),
+            job("4", "alice", "gpu", "PD", "cpu=2,mem=512M"),
+            task("5", "1", "alice", "cpu"),
+            task("5", "2", "alice", "cpu"),
+        ]
+    }
+
+    #[test]
+    fn test_group_jobs() {
+        let jobs = test_jobs();
+        let jobs: Vec<&Job> = jobs.iter().collect();
+        let keys = [GroupKey::User, GroupKey::Partition];
+        let grouped = group_jobs(&jobs, &keys, &HashSet::new());
+        assert_eq!(
+            rows(&grouped),
+            [
+                "0:user=alice (5)",
+                "1:user=alice/partition=cpu (3)",
+                "2:3",
+                "2:5_[1-2]",
+                "1:user=alice/partition=gpu (2)",
+                "2:2",
+                "2:4",
+                "0:user=bob (1)",
+                "1:user=bob/partition=gpu (1)",
+                "2:1",
+            ]
+        );
+
+        // counts per state and resources add up over every job of the group
+        let alice = grouped[0].group.as_ref().unwrap();
+        assert_eq!(alice.key, GroupKey::User);
+        assert_eq!(alice.value, "alice");
+        assert_eq!(
+            alice.states,
+            BTreeMap::from([("PD".to_owned(), 4), ("R".to_owned(), 1)])
+        );
+        assert_eq!(
+            alice.resources,
+            Resources {
+                cpus: 10,
+                mem_mb: 32 * 1024 + 512,
+                gpus: 2
+            }
+        );
+        let alice_cpu = grouped[1].group.as_ref().unwrap();
+        assert_eq!(alice_cpu.key, GroupKey::Partition);
+        assert_eq!(alice_cpu.states, BTreeMap::from([("PD".to_owned(), 3)]));
+        assert_eq!(alice_cpu.resources, Resources::default());
+
+        assert_eq!(rows(&group_jobs(&jobs[..1], &[], &HashSet::new())), ["0:1"]);
+    }
+
+    #[test]
+    fn test_group_jobs_collapsed() {
+        let jobs = test_jobs();
+        let jobs: Vec<&Job> = jobs.iter().collect();
+        let keys = [GroupKey::User, GroupKey::Partition];
+        let collapsed =
+            HashSet::from(["user=alice/partition=cpu".to_owned(), "user=bob".to_owned()]);
+        let grouped = group_jobs(&jobs, &keys, &collapsed);
+        assert_eq!(
+            rows(&grouped),
+            [
+                "0:user=alice (5)",
+                "1:user=alice/partition=cpu (3)",
+                "1:user=alice/partition=gpu (2)",
+                "2:2",
+                "2:4",
+                "0:user=bob (1)",
+            ]
+        );
+        let collapsed_flags: Vec<bool> = grouped
+            .iter()
+            .filter_map(|r| r.group.as_ref())
+            .map(|g| g.collapsed)
+            .collect();
+        assert_eq!(collapsed_flags, [false, true, false, true]);
+        // a collapsed group still counts its jobs
+        assert_eq!(grouped[5].group.as_ref().unwrap().resources.gpus, 1);
+    }
+
+    #[test]
+    fn test_collapse_arrays() {
+        let jobs = [
+            job("1", "bob", "gpu", "R", ""),
+            task("5", "1", "bob", "gpu"),
+            task("5", "2", "bob", "gpu"),
+        ];
+        let jobs: Vec<&Job> = jobs.iter().collect();
+        let collapsed = collapse_arrays(&jobs);
+        assert_eq!(rows(&collapsed), ["0:1", "0:5_[1-2]"]);
+        assert!(collapsed[1].is_array);
+        assert_eq!(collapsed[1].task_count, Some(2));
+
+        // the tasks of an array in different groups are collapsed within each group
+        let jobs = [
+            task("5", "1", "alice", "cpu"),
+            task("5", "2", "alice", "gpu"),
+            task("5", "3", "alice", "gpu"),
+        ];
+        let jobs: Vec<&Job> = jobs.iter().collect();
+        let grouped = group_jobs(&jobs, &[GroupKey::Partition], &HashSet::new());
+        assert_eq!(
+            rows(&grouped),
+            [
+                "0:partition=cpu (1)",
+                "1:5_[1-1]",
+                "0:partition=gpu (2)",
+                "1:5_[1-2]"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_resources_from_tres() {
+        assert_eq!(
+            Resources::from_tres("cpu=4,mem=16G,node=1,billing=4,gres/gpu=2"),
+            Resources {
+                cpus: 4,
+                mem_mb: 16 * 1024,
+                gpus: 2
+            }
+        );
+        assert_eq!(
+            Resources::from_tres("cpu=1,mem=500M,node=1"),
+            Resources {
+                cpus: 1,
+                mem_mb: 500,
+                gpus: 0
+            }
+        );
+        assert_eq!(Resources::from_tres(""), Resources::default());
+    }
+
+    #[test]
+    fn test_name_prefix() {
+        assert_eq!(name_prefix("train_lr3e-4"), "train");
+        assert_eq!(name_prefix("eval-large"), "eval");
+        assert_eq!(name_prefix("sweep42"), "sweep");
+        assert_eq!(name_prefix("bash"), "bash");
+        assert_eq!(name_prefix("_hidden"), "_hidden");
+    }
+}
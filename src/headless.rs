@@ -0,0 +1,313 @@
+use std::{
+    collections::HashSet,
+    fs,
+    io::{self, Write},
+    path::Path,
+    thread,
+    time::Duration,
+};
+
+use crossbeam::{
+    channel::{tick, unbounded},
+    select,
+};
+
+use crate::app::{AppMessage, Job};
+use crate::config::RemoteConfig;
+use crate::export::{self, JobsFormat};
+use crate::file_watcher::{FileWatcherHandle, LogFile};
+use crate::filter::Filter;
+use crate::grouping::{self, GroupKey};
+use crate::job_watcher::{first_node, JobLister};
+use crate::log_buffer::{line_bytes, LogUpdate, SharedLogBuffer};
+
+/// Prints the listed jobs matching `filter`.
+pub fn list(lister: &mut JobLister, filter: Option<&Filter>, format: JobsFormat) -> io::Result<()> {
+    let jobs = lister.list()?;
+    let jobs = jobs.iter().filter(|j| filter.is_none_or(|f| f.matches(j)));
+    write_stdout(export::jobs(jobs, format).as_bytes())
+}
+
+/// Writes the job list as the TUI would show it first, with `filter` and `group_by` applied, to
+/// `path`.
+pub fn export(
+    lister: &mut JobLister,
+    filter: Option<&Filter>,
+    group_by: &[GroupKey],
+    path: &Path,
+    format: JobsFormat,
+) -> io::Result<()> {
+    let jobs = lister.list()?;
+    let listed: Vec<&Job> = jobs
+        .iter()
+        .filter(|j| filter.is_none_or(|f| f.matches(j)))
+        .collect();
+    let rows = grouping::group_jobs(&listed, group_by, &HashSet::new());
+    let exported = export::row_jobs(&rows, &listed);
+    fs::write(path, export::jobs(exported, format))
+}
+
+/// Waits until the job (or all tasks of an array job) left the queue, and returns its exit code.
+pub fn wait(job_id: &str, interval: Duration) -> io::Result<i32> {
+    let mut lister = JobLister::new(vec![format!("--jobs={}", job_id)], Vec::new());
+    loop {
+        match lister.list() {
+            Ok(jobs) if jobs.is_empty() => break,
+            Ok(_) => {}
+            // what squeue says about a job that left the queue a while ago
+            Err(e) if e.to_string().contains("Invalid job id specified") => break,
+            // squeue fails now and then on a busy cluster
+            Err(e) if e.kind() == io::ErrorKind::Other => eprintln!("{}, trying again", e),
+            Err(e) => return Err(e),
+        }
+        thread::sleep(interval);
+    }
+    exit_code(job_id).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("no exit code of job {} from sacct or scontrol", job_id),
+        )
+    })
+}
+
+/// The exit code of a finished job like a shell reports it: 128 + the number of the signal that
+/// killed it, and 1 if it ended otherwise without success (e.g. a timeout). For an array job, the
+/// first failed task's.
+fn exit_code(job_id: &str) -> Option<i32> {
+    let sacct = std::process::Command::new("sacct")
+        .args([
+            "--noheader",
+            "--allocations",
+            "--parsable2",
+            "--format=State,ExitCode",
+            "--jobs",
+            job_id,
+        ])
+        .output()
+        .ok()
+        .filter(|o| o.status.success())
+        .map(|o| String::from_utf8_lossy(&o.stdout).into_owned())
+        .filter(|o| !o.trim().is_empty());
+    let output = match sacct {
+        Some(output) => output,
+        // scontrol still knows jobs that ended recently
+        None => {
+            let output = std::process::Command::new("scontrol")
+                .args(["--oneliner", "show", "job", job_id])
+                .output()
+                .ok()?;
+            String::from_utf8_lossy(&output.stdout)
+                .lines()
+                .filter_map(|l| {
+                    let field = |key: &str| l.split_whitespace().find_map(|f| f.strip_prefix(key));
+                    Some(format!("{}|{}\n", field("JobState=")?, field("ExitCode=")?))
+                })
+                .collect()
+        }
+    };
+    parse_exit_code(&output)
+}
+
+/// Parses `State|ExitCode` lines, with exit codes like `1:0` (exit code 1) or `0:9` (killed by
+/// signal 9).
+fn parse_exit_code(output: &str) -> Option<i32> {
+    let codes: Vec<i32> = output
+        .lines()
+        .filter_map(|l| {
+            let (state, code) = l.split_once('|')?;
+            let (code, signal) = code.trim().split_once(':')?;
+            let (code, signal) = (code.parse::<i32>().ok()?, signal.parse::<i32>().ok()?);
+            Some(match code {
+                0 if signal != 0 => 128 + signal,
+                0 if !state.starts_with("COMPLETED") => 1,
+                code => code,
+            })
+        })
+        .collect();
+    codes
+        .iter()
+        .copied()
+        .find(|&c| c != 0)
+        .or(codes.first().copied())
+}
+
+/// Prints the last `lines` lines of the job's log and then what's appended to it, until the job
+/// leaves the queue.
+pub fn tail(
+    job_id: &str,
+    stderr: bool,
+    lines: usize,
+    log_search: Vec<String>,
+    remote: Option<&RemoteConfig>,
+    intervals: (Duration, Duration),
+) -> io::Result<()> {
+    let (slurm_interval, file_interval) = intervals;
+    let mut lister = JobLister::new(vec![format!("--jobs={}", job_id)], log_search);
+    let not_found = || {
+        io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("job {} is not in the queue", job_id),
+        )
+    };
+    let mut waiting = false;
+    // the log is resolved once the job runs, since it can depend on the node
+    let job = loop {
+        let mut jobs = lister.list()?;
+        if jobs.len() > 1 {
+            let message = format!(
+                "job {} is an array job, tail one of its tasks like {}",
+                job_id,
+                jobs[0].id()
+            );
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, message));
+        }
+        let job = jobs.pop().ok_or_else(not_found)?;
+        if job.state_compact != "PD" {
+            break job;
+        }
+        if !waiting {
+            eprintln!("waiting for job {} to start", job_id);
+            waiting = true;
+        }
+        thread::sleep(slurm_interval);
+    };
+    let (path, error) = if stderr {
+        (&job.stderr, &job.stderr_error)
+    } else {
+        (&job.stdout, &job.stdout_error)
+    };
+    let Some(path) = path else {
+        let message = format!(
+            "job {} has no log file: {}",
+            job_id,
+            error.as_deref().unwrap_or("unknown")
+        );
+        return Err(io::Error::new(io::ErrorKind::NotFound, message));
+    };
+
+    let (sender, receiver) = unbounded();
+    let mut watcher = FileWatcherHandle::new(sender, file_interval, None);
+    let node = first_node(&job.nodelist);
+    watcher.set_files(
+        vec![LogFile::new(path, &job.id(), node.as_deref(), remote)],
+        false,
+    );
+
+    let mut follower = LogFollower {
+        log: None,
+        printed: 0,
+        lines,
+    };
+    let poll = tick(slurm_interval);
+    let mut last_error = None;
+    loop {
+        select! {
+            recv(receiver) -> msg => {
+                if let AppMessage::JobOutput(_, update) = msg.unwrap() {
+                    match update {
+                        Ok(update) => {
+                            last_error = None;
+                            follower.update(update)?;
+                        }
+                        Err(e) => last_error = Some(e.to_string()),
+                    }
+                }
+            }
+            recv(poll) -> _ => {
+                if !still_listed(&mut lister, &job)? {
+                    break;
+                }
+            }
+        }
+    }
+
+    // what the job wrote last
+    thread::sleep(file_interval);
+    while let Ok(AppMessage::JobOutput(_, Ok(update))) = receiver.try_recv() {
+        follower.update(update)?;
+    }
+    if follower.log.is_none() {
+        let message = format!(
+            "{}: {}",
+            path.display(),
+            last_error.as_deref().unwrap_or("not found")
+        );
+        return Err(io::Error::new(io::ErrorKind::NotFound, message));
+    }
+    follower.finish()
+}
+
+fn still_listed(lister: &mut JobLister, job: &Job) -> io::Result<bool> {
+    Ok(lister.list()?.iter().any(|j| j.id() == job.id()))
+}
+
+/// Prints the complete lines of a log as they are appended.
+struct LogFollower {
+    log: Option<SharedLogBuffer>,
+    /// The index after the last printed line.
+    printed: usize,
+    /// How many lines of the log to print first.
+    lines: usize,
+}
+
+impl LogFollower {
+    fn update(&mut self, update: LogUpdate) -> io::Result<()> {
+        if let LogUpdate::Opened(log) = update {
+            let end = log.read().unwrap().complete_end();
+            self.printed = end.saturating_sub(self.lines);
+            self.log = Some(log);
+        }
+        self.print()
+    }
+
+    /// Prints the rest of the log, including an unfinished last line as is.
+    fn finish(&mut self) -> io::Result<()> {
+        self.print()?;
+        match &self.log {
+            Some(log) => write_stdout(&line_bytes(log.read().unwrap().unfinished())),
+            None => Ok(()),
+        }
+    }
+
+    fn print(&mut self) -> io::Result<()> {
+        let Some(log) = &self.log else {
+            return Ok(());
+        };
+        let log = log.read().unwrap();
+        let end = log.complete_end();
+        let mut out = Vec::new();
+        for line in log.range(self.printed, end) {
+            out.extend(line_bytes(line));
+            out.push(b'\n');
+        }
+        self.printed = self.printed.max(end);
+        write_stdout(&out)
+    }
+}
+
+/// Writes to stdout, where a closed pipe (e.g. to `head`) isn't an error.
+fn write_stdout(bytes: &[u8]) -> io::Result<()> {
+    let mut stdout = io::stdout().lock();
+    match stdout.write_all(bytes).and_then(|_| stdout.flush()) {
+        Err(e) if e.kind() == io::ErrorKind::BrokenPipe => std::process::exit(0),
+        result => result,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_exit_code() {
+        assert_eq!(parse_exit_code("COMPLETED|0:0\n"), Some(0));
+        assert_eq!(parse_exit_code("FAILED|2:0\n"), Some(2));
+        assert_eq!(parse_exit_code("OUT_OF_MEMORY|0:125\n"), Some(253));
+        assert_eq!(parse_exit_code("TIMEOUT|0:0\n"), Some(1));
+        assert_eq!(
+            parse_exit_code("COMPLETED|0:0\nFAILED|1:0\nCOMPLETED|0:0\n"),
+            Some(1)
+        );
+        assert_eq!(parse_exit_code(""), None);
+    }
+}
@@ -1,8 +1,14 @@
-use std::path::PathBuf;
-use std::{io::BufRead, process::Command, thread, time::Duration};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::{
+    env, fmt, fs,
+    io::{self, BufRead},
+    process::Command,
+    thread,
+    time::Duration,
+};
 
-use crossbeam::channel::{Sender, select};
-use regex::Regex;
+use crossbeam::channel::{select, Sender};
 
 use crate::app::AppMessage;
 use crate::app::Job;
@@ -10,25 +16,149 @@ use crate::app::Job;
 struct JobWatcher {
     app: Sender<AppMessage>,
     interval: Duration,
+    args_receiver: crossbeam::channel::Receiver<(u64, Vec<String>)>,
+    /// How many times the squeue arguments changed, which the jobs are sent with.
+    generation: u64,
+    lister: JobLister,
+}
+
+/// Lists the jobs in the queue with squeue and resolves their output files.
+pub struct JobLister {
     squeue_args: Vec<String>,
-    args_receiver: crossbeam::channel::Receiver<Vec<String>>,
+    /// Patterns of files to look for when a job's output file doesn't exist, see [`LogSource`].
+    log_search: Vec<String>,
+    /// The StdOut and StdErr paths `scontrol` reported, by job id, since it's only asked once.
+    scontrol_paths: HashMap<String, [Option<PathBuf>; 2]>,
+    /// Whether `scontrol` was asked about all jobs in this refresh, which is done at most once.
+    scontrol_asked: bool,
 }
 
 pub struct JobWatcherHandle {
-    sender: crossbeam::channel::Sender<Vec<String>>,
+    sender: crossbeam::channel::Sender<(u64, Vec<String>)>,
+    generation: u64,
+}
+
+/// What the replacement symbols of an sbatch filename pattern stand for in the batch script of a
+/// job, whose output is what the log pane shows.
+struct PatternFields<'a> {
+    /// The job's own id (`%j`), which an array task has in addition to the array's job id (`%A`),
+    /// if it's known.
+    job_id: Option<&'a str>,
+    array_job_id: &'a str,
+    array_task_id: Option<&'a str>,
+    nodelist: &'a str,
+    user: &'a str,
+    name: &'a str,
+}
+
+#[derive(Clone, Copy)]
+enum Stream {
+    Stdout,
+    Stderr,
+}
+
+/// Where an output file was found when the file its filename pattern resolves to doesn't exist, e.g.
+/// because the pattern depends on the node, or the job's wrapper redirected its output.
+#[derive(Clone, Debug, PartialEq)]
+pub enum LogSource {
+    /// sbatch's default `slurm-<id>.out` in the job's working directory.
+    WorkDir,
+    /// The path `scontrol show job` reports.
+    Scontrol,
+    /// The newest file matching this pattern of `log.search` in config.toml.
+    Search(String),
+}
+
+impl fmt::Display for LogSource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LogSource::WorkDir => write!(f, "default file in WorkDir"),
+            LogSource::Scontrol => write!(f, "scontrol"),
+            LogSource::Search(pattern) => write!(f, "search pattern {}", pattern),
+        }
+    }
+}
+
+/// A replacement symbol in a filename pattern that can't be resolved from what squeue lists.
+#[derive(Debug, PartialEq)]
+pub enum PatternError {
+    /// `%j` of an array task whose own job id isn't known.
+    ArrayTaskJobId,
+    /// `%N` of a job that doesn't run on a node yet.
+    NoNode,
+    /// A symbol sbatch doesn't know, or a `%` at the end.
+    Unknown(String),
+}
+
+impl fmt::Display for PatternError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PatternError::ArrayTaskJobId => write!(f, "%j of an array task is unknown"),
+            PatternError::NoNode => write!(f, "%N is unknown until the job runs"),
+            PatternError::Unknown(symbol) => write!(f, "unknown filename pattern {}", symbol),
+        }
+    }
 }
 
 impl JobWatcher {
-    fn new(app: Sender<AppMessage>, interval: Duration, squeue_args: Vec<String>, args_receiver: crossbeam::channel::Receiver<Vec<String>>) -> Self {
+    fn new(
+        app: Sender<AppMessage>,
+        interval: Duration,
+        squeue_args: Vec<String>,
+        args_receiver: crossbeam::channel::Receiver<(u64, Vec<String>)>,
+        log_search: Vec<String>,
+    ) -> Self {
         Self {
             app,
             interval,
-            squeue_args,
             args_receiver,
+            generation: 0,
+            lister: JobLister::new(squeue_args, log_search),
         }
     }
 
     fn run(&mut self) -> Self {
+        loop {
+            // Check for args updates
+            select! {
+                recv(self.args_receiver) -> new_args => {
+                    if let Ok((generation, new_args)) = new_args {
+                        self.generation = generation;
+                        self.lister.squeue_args = new_args;
+                    }
+                },
+                default => {}
+            }
+
+            match self.lister.list() {
+                Ok(jobs) => self
+                    .app
+                    .send(AppMessage::Jobs(self.generation, jobs))
+                    .unwrap(),
+                // squeue fails now and then on a busy cluster, so it's tried again on the next
+                // refresh, instead of the jobs looking like they left the queue
+                Err(e) if e.kind() == io::ErrorKind::Other => {}
+                Err(e) => panic!("failed to execute process: {}", e),
+            }
+            thread::sleep(self.interval);
+        }
+    }
+}
+
+impl JobLister {
+    pub fn new(squeue_args: Vec<String>, log_search: Vec<String>) -> Self {
+        JobLister {
+            squeue_args,
+            log_search,
+            scontrol_paths: HashMap::new(),
+            scontrol_asked: false,
+        }
+    }
+
+    /// The jobs squeue lists with the arguments, array tasks each by itself. squeue failing is an
+    /// error of kind [`io::ErrorKind::Other`] with its message.
+    pub fn list(&mut self) -> io::Result<Vec<Job>> {
+        self.scontrol_asked = false;
         let output_separator = "###turf###";
         let fields = [
             "jobid",
@@ -48,182 +178,630 @@ impl JobWatcher {
             "ArrayTaskID", // %a
             "NodeList",    // %N
             "WorkDir",     // for fallback
+            "account",
         ];
         let output_format = fields
             .map(|s| s.to_owned() + ":" + output_separator)
             .join(",");
 
-        loop {
-            // Check for args updates
-            select! {
-                recv(self.args_receiver) -> new_args => {
-                    if let Ok(new_args) = new_args {
-                        self.squeue_args = new_args;
-                    }
-                },
-                default => {}
-            }
+        let output = Command::new("squeue")
+            .args(&self.squeue_args)
+            .arg("--array")
+            .arg("--noheader")
+            .arg("--Format")
+            .arg(&output_format)
+            .output()?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(io::Error::other(match stderr.trim() {
+                "" => format!("squeue failed: {}", output.status),
+                e => format!("squeue failed: {}", e),
+            }));
+        }
 
-            let jobs: Vec<Job> = Command::new("squeue")
-                .args(&self.squeue_args)
-                .arg("--array")
-                .arg("--noheader")
-                .arg("--Format")
-                .arg(&output_format)
-                .output()
-                .expect("failed to execute process")
-                .stdout
-                .lines()
-                .map(|l| l.unwrap().trim().to_string())
-                .filter_map(|l| {
-                    let parts: Vec<_> = l.split(output_separator).collect();
-
-                    if parts.len() != fields.len() + 1 {
-                        return None;
-                    }
+        let jobs: Vec<Job> = output
+            .stdout
+            .lines()
+            .map(|l| l.unwrap().trim().to_string())
+            .filter_map(|l| {
+                let parts: Vec<_> = l.split(output_separator).collect();
 
-                    let id = parts[0];
-                    let name = parts[1];
-                    let state = parts[2];
-                    let user = parts[3];
-                    let time = parts[4];
-                    let tres = parts[5];
-                    let partition = parts[6];
-                    let nodelist = parts[7];
-                    let stdout = parts[8];
-                    let stderr = parts[9];
-                    let command = parts[10];
-                    let state_compact = parts[11];
-                    let reason = parts[12];
-
-                    let array_job_id = parts[13];
-                    let array_task_id = parts[14];
-                    let node_list = parts[15];
-                    let working_dir = parts[16];
-
-                    Some(Job {
-                        job_id: id.to_owned(),
-                        array_id: array_job_id.to_owned(),
-                        array_step: match array_task_id {
-                            "N/A" => None,
-                            _ => Some(array_task_id.to_owned()),
-                        },
-                        name: name.to_owned(),
-                        state: state.to_owned(),
-                        state_compact: state_compact.to_owned(),
-                        reason: if reason == "None" {
-                            None
-                        } else {
-                            Some(reason.to_owned())
-                        },
-                        user: user.to_owned(),
-                        time: time.to_owned(),
-                        tres: tres.to_owned(),
-                        partition: partition.to_owned(),
-                        nodelist: nodelist.to_owned(),
-                        command: command.to_owned(),
-                        stdout: Self::resolve_path(
-                            stdout,
-                            array_job_id,
-                            array_task_id,
-                            id,
-                            node_list,
-                            user,
-                            name,
-                            working_dir,
-                        ),
-                        stderr: Self::resolve_path(
-                            stderr,
-                            array_job_id,
-                            array_task_id,
-                            id,
-                            node_list,
-                            user,
-                            name,
-                            working_dir,
-                        ), // TODO fill all fields
-                    })
+                if parts.len() != fields.len() + 1 {
+                    return None;
+                }
+
+                let id = parts[0];
+                let name = parts[1];
+                let state = parts[2];
+                let user = parts[3];
+                let time = parts[4];
+                let tres = parts[5];
+                let partition = parts[6];
+                let nodelist = parts[7];
+                let stdout = parts[8];
+                let stderr = parts[9];
+                let command = parts[10];
+                let state_compact = parts[11];
+                let reason = parts[12];
+
+                let array_job_id = parts[13];
+                let array_task_id = parts[14];
+                let node_list = parts[15];
+                let working_dir = parts[16];
+                let account = parts[17];
+
+                let fields = PatternFields {
+                    // an array task is listed as `<array job id>_<task id>`, without its own id
+                    job_id: Some(id).filter(|id| id.chars().all(|c| c.is_ascii_digit())),
+                    array_job_id,
+                    array_task_id: Some(array_task_id).filter(|&a| a != "N/A"),
+                    nodelist: node_list,
+                    user,
+                    name,
+                };
+                let stdout = resolve_path(stdout, &fields, working_dir);
+                let stderr = resolve_path(stderr, &fields, working_dir);
+                // a pending job has no output to find yet
+                let (stdout_found, stderr_found) = if state_compact == "PD" {
+                    (None, None)
+                } else {
+                    (
+                        self.find_log(id, &stdout, Stream::Stdout, &fields, working_dir),
+                        self.find_log(id, &stderr, Stream::Stderr, &fields, working_dir),
+                    )
+                };
+                let (stdout, stdout_source) = match (stdout, stdout_found) {
+                    (_, Some((path, source))) => (Ok(path), Some(source)),
+                    (stdout, None) => (stdout, None),
+                };
+                let (stderr, stderr_source) = match (stderr, stderr_found) {
+                    (_, Some((path, source))) => (Ok(path), Some(source)),
+                    (stderr, None) => (stderr, None),
+                };
+
+                Some(Job {
+                    job_id: id.to_owned(),
+                    array_id: array_job_id.to_owned(),
+                    array_step: match array_task_id {
+                        "N/A" => None,
+                        _ => Some(array_task_id.to_owned()),
+                    },
+                    name: name.to_owned(),
+                    state: state.to_owned(),
+                    state_compact: state_compact.to_owned(),
+                    reason: if reason == "None" {
+                        None
+                    } else {
+                        Some(reason.to_owned())
+                    },
+                    user: user.to_owned(),
+                    account: account.to_owned(),
+                    time: time.to_owned(),
+                    tres: tres.to_owned(),
+                    partition: partition.to_owned(),
+                    nodelist: nodelist.to_owned(),
+                    command: command.to_owned(),
+                    stdout: stdout.as_ref().ok().cloned(),
+                    stderr: stderr.as_ref().ok().cloned(),
+                    stdout_error: stdout.err().map(|e| e.to_string()),
+                    stderr_error: stderr.err().map(|e| e.to_string()),
+                    stdout_source,
+                    stderr_source,
                 })
-                .collect();
-            self.app.send(AppMessage::Jobs(jobs)).unwrap();
-            thread::sleep(self.interval);
-        }
+            })
+            .collect();
+        self.scontrol_paths
+            .retain(|id, _| jobs.iter().any(|j| j.job_id == *id));
+        Ok(jobs)
     }
+}
 
-    fn resolve_path(
-        path: &str,
-        array_master: &str,
-        array_id: &str,
+impl JobLister {
+    /// A fallback for a job's output file if the path from its filename pattern doesn't exist: the
+    /// first existing file of the [`LogSource`]s, in the order they are declared.
+    fn find_log(
+        &mut self,
         id: &str,
-        host: &str,
-        user: &str,
-        name: &str,
+        resolved: &Result<PathBuf, PatternError>,
+        stream: Stream,
+        fields: &PatternFields,
         working_dir: &str,
-    ) -> Option<PathBuf> {
-        // see https://slurm.schedmd.com/sbatch.html#SECTION_%3CB%3Efilename-pattern%3C/B%3E
-        lazy_static::lazy_static! {
-            static ref RE: Regex = Regex::new(r"%(%|A|a|J|j|N|n|s|t|u|x)").unwrap();
+    ) -> Option<(PathBuf, LogSource)> {
+        if resolved.as_ref().is_ok_and(|p| p.is_file()) {
+            return None;
         }
 
-        let mut path = path.to_owned();
-        let slurm_no_val = "4294967294";
-        let array_id = if array_id == "N/A" {
-            slurm_no_val
-        } else {
-            array_id
+        if let Ok(default) = resolve_path("", fields, working_dir) {
+            if default.is_file() {
+                return Some((default, LogSource::WorkDir));
+            }
+        }
+
+        if !self.scontrol_paths.contains_key(id) && !self.scontrol_asked {
+            self.scontrol_asked = true;
+            self.scontrol_paths.extend(scontrol_paths());
+        }
+        // a job scontrol didn't report isn't asked about again
+        let scontrol = self.scontrol_paths.entry(id.to_owned()).or_default();
+        if let Some(path) = scontrol[stream as usize].as_ref().filter(|p| p.is_file()) {
+            return Some((path.clone(), LogSource::Scontrol));
+        }
+
+        self.log_search.iter().find_map(|pattern| {
+            let expanded = expand_pattern(pattern, fields).ok()?;
+            let path = match expanded.strip_prefix("~/") {
+                Some(rest) => PathBuf::from(env::var_os("HOME")?).join(rest),
+                None => Path::new(working_dir).join(expanded),
+            };
+            Some((newest_match(&path)?, LogSource::Search(pattern.clone())))
+        })
+    }
+}
+
+/// The StdOut and StdErr paths of all jobs `scontrol` knows, in one call instead of one per job.
+fn scontrol_paths() -> Vec<(String, [Option<PathBuf>; 2])> {
+    match Command::new("scontrol")
+        .args(["--oneliner", "show", "job"])
+        .output()
+    {
+        Ok(output) if output.status.success() => {
+            parse_scontrol_paths(&String::from_utf8_lossy(&output.stdout))
+        }
+        _ => Vec::new(),
+    }
+}
+
+/// The paths of each job in `scontrol --oneliner show job` output, by its job id and, for an array
+/// task, also by `<array job id>_<task id>`.
+fn parse_scontrol_paths(output: &str) -> Vec<(String, [Option<PathBuf>; 2])> {
+    let mut paths = Vec::new();
+    for line in output.lines() {
+        let fields = scontrol_fields(line);
+        let field = |key: &str| {
+            fields
+                .iter()
+                .find(|(k, _)| *k == key)
+                .map(|(_, v)| *v)
+                .filter(|v| !v.is_empty())
         };
+        let job_paths = [
+            field("StdOut").map(PathBuf::from),
+            field("StdErr").map(PathBuf::from),
+        ];
+        if let (Some(array_id), Some(task_id)) = (field("ArrayJobId"), field("ArrayTaskId")) {
+            paths.push((format!("{}_{}", array_id, task_id), job_paths.clone()));
+        }
+        if let Some(id) = field("JobId") {
+            paths.push((id.to_owned(), job_paths));
+        }
+    }
+    paths
+}
 
-        if path.is_empty() {
-            // never happens right now, because `squeue -O stdout` seems to always return something
-            path = if array_id == slurm_no_val {
-                PathBuf::from(working_dir).join("slurm-%J.out")
-            } else {
-                PathBuf::from(working_dir).join("slurm-%A_%a.out")
+/// The `Key=value` fields of a line of `scontrol --oneliner` output. A value runs until the next
+/// key, so it can have spaces (e.g. a path or a command with arguments), and keys start with an
+/// uppercase letter, unlike the `key=value` arguments a command can have.
+pub fn scontrol_fields(line: &str) -> Vec<(&str, &str)> {
+    let is_key = |word: &str| {
+        word.split_once('=').is_some_and(|(key, _)| {
+            key.starts_with(|c: char| c.is_ascii_uppercase())
+                && key
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '/' | ':' | '_'))
+        })
+    };
+    let mut fields: Vec<(&str, &str)> = Vec::new();
+    let mut start = None;
+    let mut offset = 0;
+    for word in line.split(' ') {
+        if is_key(word) {
+            if let Some(start) = start {
+                fields.push(line[start..offset - 1].split_once('=').unwrap());
             }
-            .to_str()
-            .unwrap()
-            .to_owned();
-        };
+            start = Some(offset);
+        }
+        offset += word.len() + 1;
+    }
+    if let Some(start) = start {
+        fields.push(line[start..].trim_end().split_once('=').unwrap());
+    }
+    fields
+}
 
-        for cap in RE
-            .captures_iter(&path.clone())
-            .collect::<Vec<_>>() // TODO: this is stupid, there has to be a better way to reverse the captures...
-            .iter()
-            .rev()
-        {
-            let m = cap.get(0).unwrap();
-            let replacement = match m.as_str() {
-                "%%" => "%",
-                "%A" => array_master,
-                "%a" => array_id,
-                "%J" => id,
-                "%j" => id,
-                "%N" => host.split(',').next().unwrap_or(host),
-                "%n" => "0",
-                "%s" => "batch",
-                "%t" => "0",
-                "%u" => user,
-                "%x" => name,
-                _ => unreachable!(),
-            };
+/// The most recently modified file matching `pattern`, which can have `*` and `?` wildcards in its
+/// file name (but not in its directories).
+fn newest_match(pattern: &Path) -> Option<PathBuf> {
+    let name = pattern.file_name()?.to_str()?;
+    fs::read_dir(pattern.parent()?)
+        .ok()?
+        .flatten()
+        .filter(|e| {
+            e.file_name()
+                .to_str()
+                .is_some_and(|n| wildcard_match(name, n))
+        })
+        .filter_map(|e| {
+            let metadata = e.metadata().ok().filter(|m| m.is_file())?;
+            Some((metadata.modified().ok()?, e.path()))
+        })
+        .max()
+        .map(|(_, path)| path)
+}
+
+fn wildcard_match(pattern: &str, name: &str) -> bool {
+    let (pattern, name): (Vec<char>, Vec<char>) =
+        (pattern.chars().collect(), name.chars().collect());
+    let (mut p, mut n) = (0, 0);
+    // where the last `*` is and where in `name` it would match up to, to backtrack to
+    let mut star = None;
+    while n < name.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == name[n]) {
+            p += 1;
+            n += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p, n));
+            p += 1;
+        } else if let Some((star_p, star_n)) = star {
+            star = Some((star_p, star_n + 1));
+            p = star_p + 1;
+            n = star_n + 1;
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// The path of a job's output file from its sbatch filename pattern, relative to the job's working
+/// directory. sbatch's default is used for an empty pattern.
+fn resolve_path(
+    pattern: &str,
+    fields: &PatternFields,
+    working_dir: &str,
+) -> Result<PathBuf, PatternError> {
+    let pattern = match pattern {
+        // never happens right now, because `squeue -O stdout` seems to always return something
+        "" if fields.array_task_id.is_some() => "slurm-%A_%a.out",
+        "" => "slurm-%j.out",
+        _ => pattern,
+    };
+    let path = expand_pattern(pattern, fields)?;
+    Ok(PathBuf::from(working_dir).join(path)) // works even if `path` is absolute
+}
 
-            path.replace_range(m.range(), replacement);
+/// The most digits sbatch zero-pads a value in a filename pattern to.
+const MAX_PADDING: usize = 10;
+
+/// Replaces the symbols of an sbatch filename pattern, see
+/// https://slurm.schedmd.com/sbatch.html#SECTION_FILENAME-PATTERN
+///
+/// A number between `%` and the symbol zero-pads numeric values to that many digits, up to
+/// [`MAX_PADDING`]. A pattern containing `\\` isn't expanded, and the `\\` are removed.
+fn expand_pattern(pattern: &str, fields: &PatternFields) -> Result<String, PatternError> {
+    // what slurm uses for the task id of a job that isn't an array task
+    const NO_VAL: &str = "4294967294";
+
+    if pattern.contains("\\\\") {
+        return Ok(pattern.replace("\\\\", ""));
+    }
+
+    let mut path = String::with_capacity(pattern.len());
+    let mut chars = pattern.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            path.push(c);
+            continue;
+        }
+        let mut width = String::new();
+        while let Some(digit) = chars.next_if(char::is_ascii_digit) {
+            width.push(digit);
+        }
+        let padded = |value: &str| {
+            // sbatch pads wider widths, even ones that don't fit in a usize, to the maximum
+            let width = match width.parse::<usize>() {
+                Ok(width) => width.min(MAX_PADDING),
+                Err(_) if width.is_empty() => 0,
+                Err(_) => MAX_PADDING,
+            };
+            format!("{:0>width$}", value)
+        };
+        let task_id = fields.array_task_id.unwrap_or(NO_VAL);
+        match chars.next() {
+            Some('%') => path.push('%'),
+            Some('A') => path.push_str(&padded(fields.array_job_id)),
+            Some('a') => path.push_str(&padded(task_id)),
+            // the last digit, i.e. modulo 10
+            Some('b') => path.push_str(&padded(
+                task_id
+                    .get(task_id.len().saturating_sub(1)..)
+                    .unwrap_or_default(),
+            )),
+            // the batch script has no step id to append to %J
+            Some('J' | 'j') => {
+                path.push_str(&padded(fields.job_id.ok_or(PatternError::ArrayTaskJobId)?))
+            }
+            // the batch script runs as the first task on the first node of the job
+            Some('N') => path.push_str(&first_node(fields.nodelist).ok_or(PatternError::NoNode)?),
+            Some('n' | 't') => path.push_str(&padded("0")),
+            Some('s') => path.push_str("batch"),
+            Some('u') => path.push_str(fields.user),
+            Some('x') => path.push_str(fields.name),
+            Some(c) => return Err(PatternError::Unknown(format!("%{}{}", width, c))),
+            None => return Err(PatternError::Unknown(format!("%{}", width))),
         }
+    }
+    Ok(path)
+}
 
-        Some(PathBuf::from(working_dir).join(path)) // works even if `path` is absolute
+/// The nodes of a Slurm node list like `gpu[03-05,07],cpu1`, in order.
+pub fn expand_nodelist(nodelist: &str) -> Vec<String> {
+    let mut nodes = Vec::new();
+    let mut rest = nodelist;
+    while !rest.is_empty() {
+        let end = rest.find([',', '[']).unwrap_or(rest.len());
+        let (prefix, after) = rest.split_at(end);
+        rest = match after.strip_prefix('[') {
+            Some(after) => {
+                let (ranges, after) = after.split_once(']').unwrap_or((after, ""));
+                let (suffix, after) = after.split_at(after.find(',').unwrap_or(after.len()));
+                for range in ranges.split(',') {
+                    let bounds = range.split_once('-').and_then(|(from, to)| {
+                        Some((
+                            from.len(),
+                            from.parse::<u64>().ok()?,
+                            to.parse::<u64>().ok()?,
+                        ))
+                    });
+                    match bounds {
+                        // keeping the zero padding of the range
+                        Some((width, from, to)) => nodes.extend(
+                            (from..=to).map(|n| format!("{}{:0width$}{}", prefix, n, suffix)),
+                        ),
+                        None => nodes.push(format!("{}{}{}", prefix, range, suffix)),
+                    }
+                }
+                after
+            }
+            None => {
+                if !prefix.is_empty() {
+                    nodes.push(prefix.to_owned());
+                }
+                after
+            }
+        };
+        rest = rest.strip_prefix(',').unwrap_or(rest);
     }
+    nodes
+}
+
+/// The first node of a Slurm node list like `gpu[03-05,07],cpu1`.
+pub fn first_node(nodelist: &str) -> Option<String> {
+    expand_nodelist(nodelist).into_iter().next()
 }
 
 impl JobWatcherHandle {
-    pub fn new(app: Sender<AppMessage>, interval: Duration, squeue_args: Vec<String>) -> Self {
+    pub fn new(
+        app: Sender<AppMessage>,
+        interval: Duration,
+        squeue_args: Vec<String>,
+        log_search: Vec<String>,
+    ) -> Self {
         let (args_sender, args_receiver) = crossbeam::channel::unbounded();
-        let mut actor = JobWatcher::new(app, interval, squeue_args, args_receiver);
+        let mut actor = JobWatcher::new(app, interval, squeue_args, args_receiver, log_search);
         thread::spawn(move || actor.run());
 
-        Self { sender: args_sender }
+        Self {
+            sender: args_sender,
+            generation: 0,
+        }
+    }
+
+    pub fn update_squeue_args(&mut self, new_args: Vec<String>) {
+        self.generation += 1;
+        let _ = self.sender.send((self.generation, new_args));
+    }
+
+    /// The generation of the current squeue arguments, which the jobs listed with them are sent
+    /// with.
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const JOB: PatternFields = PatternFields {
+        job_id: Some("128"),
+        array_job_id: "128",
+        array_task_id: None,
+        nodelist: "gpu[03-05,07],cpu1",
+        user: "alice",
+        name: "train",
+    };
+
+    // task 13 of array job 200, which has its own job id 213
+    const ARRAY_TASK: PatternFields = PatternFields {
+        job_id: Some("213"),
+        array_job_id: "200",
+        array_task_id: Some("13"),
+        nodelist: "node7",
+        user: "bob",
+        name: "sweep",
+    };
+
+    fn expand(pattern: &str, fields: &PatternFields) -> String {
+        expand_pattern(pattern, fields).unwrap()
+    }
+
+    #[test]
+    fn test_man_page_examples() {
+        // the examples of the sbatch man page, for the batch script (step "batch", task 0)
+        assert_eq!(expand("job%J.out", &JOB), "job128.out");
+        assert_eq!(expand("job%4j.out", &JOB), "job0128.out");
+        assert_eq!(expand("job%2j-%2t.out", &JOB), "job128-00.out");
+    }
+
+    #[test]
+    fn test_symbols() {
+        let cases = [
+            ("%%", &JOB, "%"),
+            ("%%j", &JOB, "%j"),
+            ("%A_%a", &ARRAY_TASK, "200_13"),
+            ("%A_%a", &JOB, "128_4294967294"),
+            ("%b", &ARRAY_TASK, "3"),
+            ("%j", &ARRAY_TASK, "213"),
+            ("%J", &ARRAY_TASK, "213"),
+            ("%N", &JOB, "gpu03"),
+            ("%N", &ARRAY_TASK, "node7"),
+            ("%n", &JOB, "0"),
+            ("%s", &JOB, "batch"),
+            ("%t", &ARRAY_TASK, "0"),
+            ("%u", &ARRAY_TASK, "bob"),
+            ("%x", &ARRAY_TASK, "sweep"),
+            ("%u/%x-%j.log", &JOB, "alice/train-128.log"),
+        ];
+        for (pattern, fields, expected) in cases {
+            assert_eq!(expand(pattern, fields), expected, "{}", pattern);
+        }
+    }
+
+    #[test]
+    fn test_padding() {
+        assert_eq!(expand("%3a", &ARRAY_TASK), "013");
+        assert_eq!(expand("%5A_%1a", &ARRAY_TASK), "00200_13");
+        assert_eq!(expand("%6j", &ARRAY_TASK), "000213");
+        assert_eq!(expand("%3t", &JOB), "000");
+        // sbatch pads to at most 10 digits
+        assert_eq!(expand("%10j", &JOB), "0000000128");
+        assert_eq!(expand("%12j", &JOB), "0000000128");
+        assert_eq!(expand("%99999999999999999999999j", &JOB), "0000000128");
+        // padding only applies to numbers
+        assert_eq!(expand("%4N-%8u-%3s-%9x", &JOB), "gpu03-alice-batch-train");
+    }
+
+    #[test]
+    fn test_escapes() {
+        assert_eq!(expand("job\\\\%j.out", &JOB), "job%j.out");
+        assert_eq!(expand("\\\\%A_%a", &ARRAY_TASK), "%A_%a");
+        // a single backslash is part of the file name
+        assert_eq!(expand("job\\%j.out", &JOB), "job\\128.out");
+    }
+
+    #[test]
+    fn test_unresolved() {
+        let pending = PatternFields {
+            nodelist: "",
+            ..JOB
+        };
+        assert_eq!(
+            expand_pattern("%N.out", &pending),
+            Err(PatternError::NoNode)
+        );
+        let unknown_id = PatternFields {
+            job_id: None,
+            ..ARRAY_TASK
+        };
+        assert_eq!(
+            expand_pattern("%j.out", &unknown_id),
+            Err(PatternError::ArrayTaskJobId)
+        );
+        assert_eq!(expand("%A_%a.out", &unknown_id), "200_13.out");
+        assert_eq!(
+            expand_pattern("%3z", &JOB),
+            Err(PatternError::Unknown("%3z".to_owned()))
+        );
+        assert_eq!(
+            expand_pattern("100%", &JOB),
+            Err(PatternError::Unknown("%".to_owned()))
+        );
+    }
+
+    #[test]
+    fn test_expand_nodelist() {
+        assert_eq!(
+            expand_nodelist("gpu[08-10,07],cpu1"),
+            vec!["gpu08", "gpu09", "gpu10", "gpu07", "cpu1"]
+        );
+        assert_eq!(
+            expand_nodelist("n[1-2]-ib,login"),
+            vec!["n1-ib", "n2-ib", "login"]
+        );
+        assert_eq!(expand_nodelist("node5"), vec!["node5"]);
+        assert!(expand_nodelist("").is_empty());
+
+        assert_eq!(first_node("gpu[01-02]-ib").as_deref(), Some("gpu01-ib"));
+        assert_eq!(first_node("gpu[03-05,07],cpu1").as_deref(), Some("gpu03"));
+        assert_eq!(first_node("cpu1,gpu2").as_deref(), Some("cpu1"));
+        assert_eq!(first_node(""), None);
+    }
+
+    #[test]
+    fn test_wildcard_match() {
+        assert!(wildcard_match("*128*.out", "train-128-n1.out"));
+        assert!(wildcard_match("slurm-???.out", "slurm-128.out"));
+        assert!(wildcard_match("*", ""));
+        assert!(wildcard_match("a*b*c", "aXbYbZc"));
+        assert!(!wildcard_match("*128*.out", "train-128.err"));
+        assert!(!wildcard_match("slurm-??.out", "slurm-128.out"));
+    }
+
+    #[test]
+    fn test_scontrol_fields() {
+        let line = "JobId=128 JobName=my run Command=/w/train.sh --lr=0.1 epochs=3 CPUs/Task=4 WorkDir=/w/a b";
+        assert_eq!(
+            scontrol_fields(line),
+            [
+                ("JobId", "128"),
+                ("JobName", "my run"),
+                ("Command", "/w/train.sh --lr=0.1 epochs=3"),
+                ("CPUs/Task", "4"),
+                ("WorkDir", "/w/a b"),
+            ]
+        );
+        assert!(scontrol_fields("").is_empty());
+    }
+
+    #[test]
+    fn test_parse_scontrol_paths() {
+        let output = "JobId=128 JobName=train UserId=alice(1000) WorkDir=/w StdErr=/w/my err.log StdIn=/dev/null StdOut=/w/out.log Power=\n\
+            JobId=213 ArrayJobId=200 ArrayTaskId=13 StdErr= StdOut=/w/sweep-200_13.out\n";
+        let task = [Some(PathBuf::from("/w/sweep-200_13.out")), None];
+        assert_eq!(
+            parse_scontrol_paths(output),
+            [
+                (
+                    "128".to_owned(),
+                    [
+                        Some(PathBuf::from("/w/out.log")),
+                        Some(PathBuf::from("/w/my err.log"))
+                    ]
+                ),
+                ("200_13".to_owned(), task.clone()),
+                ("213".to_owned(), task),
+            ]
+        );
+        assert!(parse_scontrol_paths("").is_empty());
     }
 
-    pub fn update_squeue_args(&self, new_args: Vec<String>) {
-        let _ = self.sender.send(new_args);
+    #[test]
+    fn test_resolve_path() {
+        assert_eq!(
+            resolve_path("", &JOB, "/home/alice"),
+            Ok(PathBuf::from("/home/alice/slurm-128.out"))
+        );
+        assert_eq!(
+            resolve_path("", &ARRAY_TASK, "/w"),
+            Ok(PathBuf::from("/w/slurm-200_13.out"))
+        );
+        assert_eq!(
+            resolve_path("logs/%x.out", &JOB, "/w"),
+            Ok(PathBuf::from("/w/logs/train.out"))
+        );
+        assert_eq!(
+            resolve_path("/tmp/%j.out", &JOB, "/w"),
+            Ok(PathBuf::from("/tmp/128.out"))
+        );
     }
 }
@@ -0,0 +1,282 @@
+use std::{
+    collections::VecDeque,
+    sync::{Arc, RwLock},
+};
+
+/// The lines of a log file, appended to by the file reader and shared with the UI.
+///
+/// Lines are numbered from the start of the file. With `max_lines`, the oldest lines are dropped
+/// once the buffer is full, so the first retained line can have an index greater than 0.
+#[derive(Default)]
+pub struct LogBuffer {
+    lines: VecDeque<String>,
+    /// The line still being written, after the last newline.
+    partial: String,
+    first_line: usize,
+    max_lines: Option<usize>,
+}
+
+pub type SharedLogBuffer = Arc<RwLock<LogBuffer>>;
+
+/// Bytes that aren't valid UTF-8 are stored as chars in this private use range (U+10FF00 + byte), so
+/// they can be shown as the original bytes.
+const RAW_BYTE_BASE: u32 = 0x10FF00;
+
+/// The char standing for a byte that isn't valid UTF-8.
+pub fn byte_char(b: u8) -> char {
+    char::from_u32(RAW_BYTE_BASE + b as u32).unwrap()
+}
+
+/// The byte a char from [`byte_char`] stands for.
+pub fn raw_byte(c: char) -> Option<u8> {
+    (c as u32).checked_sub(RAW_BYTE_BASE).map(|b| b as u8)
+}
+
+/// The bytes of a line as they were in the file.
+pub fn line_bytes(l: &str) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(l.len());
+    for c in l.chars() {
+        match raw_byte(c) {
+            Some(b) => bytes.push(b),
+            None => bytes.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes()),
+        }
+    }
+    bytes
+}
+
+/// Decodes UTF-8 from the start of `bytes`, keeping invalid bytes as [`byte_char`]s. A sequence cut
+/// off at the end is left in `bytes`, to be completed by the next read.
+pub fn decode_lossy(bytes: &mut Vec<u8>) -> String {
+    let mut s = String::with_capacity(bytes.len());
+    let mut rest = &bytes[..];
+    while !rest.is_empty() {
+        match std::str::from_utf8(rest) {
+            Ok(valid) => {
+                s.push_str(valid);
+                rest = &[];
+            }
+            Err(e) => {
+                let (valid, invalid) = rest.split_at(e.valid_up_to());
+                s.push_str(std::str::from_utf8(valid).unwrap());
+                let Some(len) = e.error_len() else {
+                    rest = invalid;
+                    break;
+                };
+                s.extend(invalid[..len].iter().map(|&b| byte_char(b)));
+                rest = &invalid[len..];
+            }
+        }
+    }
+    let decoded = bytes.len() - rest.len();
+    bytes.drain(..decoded);
+    s
+}
+
+/// What changed in the log shown in the UI.
+pub enum LogUpdate {
+    /// A (new) log file is shown.
+    Opened(SharedLogBuffer),
+    /// Lines were appended to the current log; lines from this index on are new or changed.
+    Appended(usize),
+}
+
+impl LogBuffer {
+    pub fn new(max_lines: Option<usize>) -> Self {
+        LogBuffer {
+            max_lines: max_lines.filter(|&n| n > 0),
+            ..Default::default()
+        }
+    }
+
+    pub fn shared(max_lines: Option<usize>) -> SharedLogBuffer {
+        Arc::new(RwLock::new(LogBuffer::new(max_lines)))
+    }
+
+    /// Appends text read from the file and returns the index of the first line that changed.
+    pub fn append(&mut self, s: &str) -> usize {
+        let changed = self.first_line + self.lines.len();
+        let mut parts = s.split('\n');
+        self.partial.push_str(parts.next().unwrap_or_default());
+        for part in parts {
+            let mut line = std::mem::replace(&mut self.partial, part.to_owned());
+            if line.ends_with('\r') {
+                line.pop();
+            }
+            self.lines.push_back(line);
+        }
+
+        if let Some(max_lines) = self.max_lines {
+            let excess = self.lines.len().saturating_sub(max_lines);
+            self.lines.drain(..excess);
+            self.first_line += excess;
+        }
+        changed
+    }
+
+    /// Adds the lines of `s` that are complete, each starting with `tag`, and returns the index of the
+    /// first new line, if any. The unfinished rest of a line is kept in `partial`, which `s`
+    /// continues. This is how several files are merged into one log, which can't show unfinished
+    /// lines since the files' lines would be mixed up.
+    pub fn append_lines(&mut self, partial: &mut String, s: &str, tag: &str) -> Option<usize> {
+        partial.push_str(s);
+        let end = partial.rfind('\n')?;
+        let changed = self.end();
+        for line in partial[..end].split('\n') {
+            self.append(tag);
+            self.append(line);
+            self.append("\n");
+        }
+        partial.drain(..=end);
+        Some(changed)
+    }
+
+    /// Adds a line that isn't part of the file, e.g. to show that the file was truncated, and
+    /// returns the index of the first line that changed.
+    pub fn append_marker(&mut self, text: &str) -> usize {
+        let changed = self.first_line + self.lines.len();
+        if !self.partial.is_empty() {
+            self.append("\n");
+        }
+        self.append(&format!("\x1b[7m── {} ──\x1b[0m\n", text));
+        changed
+    }
+
+    /// The index of the oldest line still in the buffer.
+    pub fn first_line(&self) -> usize {
+        self.first_line
+    }
+
+    /// The index after the last complete line, i.e. not counting the line still being written.
+    pub fn complete_end(&self) -> usize {
+        self.first_line + self.lines.len()
+    }
+
+    /// The index after the last line.
+    pub fn end(&self) -> usize {
+        self.first_line + self.lines.len() + self.partial_line().is_some() as usize
+    }
+
+    /// The line still being written, as far as it was read.
+    pub fn unfinished(&self) -> &str {
+        &self.partial
+    }
+
+    /// The lines with indices in `from..to`, limited to the lines in the buffer.
+    ///
+    /// Lines may still contain carriage returns and escape sequences, see [`crate::ansi::parse`].
+    pub fn range(&self, from: usize, to: usize) -> impl DoubleEndedIterator<Item = &str> {
+        let complete_end = self.first_line + self.lines.len();
+        let start = from.clamp(self.first_line, complete_end) - self.first_line;
+        let end = to.clamp(self.first_line, complete_end) - self.first_line;
+        let partial = self
+            .partial_line()
+            .filter(|_| from <= complete_end && to > complete_end);
+        self.lines
+            .range(start..end.max(start))
+            .map(String::as_str)
+            .chain(partial)
+    }
+
+    /// The lines from `from` on, with their indices.
+    pub fn lines_from(&self, from: usize) -> impl Iterator<Item = (usize, &str)> {
+        let from = from.max(self.first_line);
+        self.range(from, usize::MAX)
+            .enumerate()
+            .map(move |(i, l)| (from + i, l))
+    }
+
+    /// The line still being written, from its last carriage return on, i.e. the latest progress bar
+    /// update. `None` if nothing of it is written yet.
+    fn partial_line(&self) -> Option<&str> {
+        let partial = self.partial.trim_end_matches('\r');
+        let latest = partial.rfind('\r').map_or(partial, |i| &partial[i + 1..]);
+        Some(latest).filter(|p| !p.is_empty())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(buffer: &LogBuffer) -> Vec<&str> {
+        buffer.range(0, usize::MAX).collect()
+    }
+
+    #[test]
+    fn test_append() {
+        let mut buffer = LogBuffer::new(None);
+        assert_eq!(buffer.append("a\n\nb\r"), 0);
+        assert_eq!(lines(&buffer), vec!["a", "", "b"]);
+        assert_eq!(buffer.append("\nc 1%\rc 2%"), 2);
+        assert_eq!(lines(&buffer), vec!["a", "", "b", "c 2%"]);
+        assert_eq!(buffer.end(), 4);
+        assert_eq!(buffer.append("\rc 3%\n"), 3);
+        assert_eq!(lines(&buffer), vec!["a", "", "b", "c 1%\rc 2%\rc 3%"]);
+        assert_eq!(buffer.range(1, 3).collect::<Vec<_>>(), vec!["", "b"]);
+        assert_eq!(buffer.range(3, 3).count(), 0);
+    }
+
+    #[test]
+    fn test_partial_line() {
+        let mut buffer = LogBuffer::new(None);
+        assert_eq!(buffer.append("a\nloading"), 0);
+        assert_eq!(lines(&buffer), vec!["a", "loading"]);
+        assert_eq!(buffer.append(" data"), 1);
+        assert_eq!(lines(&buffer), vec!["a", "loading data"]);
+        buffer.append("\r 10%\r 20%");
+        assert_eq!(lines(&buffer), vec!["a", " 20%"]);
+        buffer.append("\r");
+        assert_eq!(lines(&buffer), vec!["a", " 20%"]);
+        assert_eq!(buffer.end(), 2);
+    }
+
+    #[test]
+    fn test_append_marker() {
+        let mut buffer = LogBuffer::new(None);
+        buffer.append("a\nb");
+        assert_eq!(buffer.append_marker("file truncated"), 1);
+        buffer.append("c\n");
+        assert_eq!(
+            lines(&buffer),
+            vec!["a", "b", "\x1b[7m── file truncated ──\x1b[0m", "c"]
+        );
+    }
+
+    #[test]
+    fn test_decode_lossy() {
+        // "é" split across two reads, and a latin-1 "é"
+        let mut bytes = b"caf\xc3".to_vec();
+        assert_eq!(decode_lossy(&mut bytes), "caf");
+        assert_eq!(bytes, b"\xc3");
+        bytes.extend_from_slice(b"\xa9 caf\xe9!");
+        let s = decode_lossy(&mut bytes);
+        assert!(bytes.is_empty());
+        assert_eq!(s, format!("é caf{}!", byte_char(0xe9)));
+        assert_eq!(line_bytes(&s), b"\xc3\xa9 caf\xe9!");
+    }
+
+    #[test]
+    fn test_append_lines() {
+        let mut buffer = LogBuffer::new(None);
+        let (mut stdout, mut stderr) = (String::new(), String::new());
+        assert_eq!(buffer.append_lines(&mut stdout, "out 1\nout", ""), Some(0));
+        assert_eq!(buffer.append_lines(&mut stderr, "err 1\r\n", "E:"), Some(1));
+        assert_eq!(buffer.append_lines(&mut stdout, " 2", ""), None);
+        assert_eq!(buffer.append_lines(&mut stdout, "\n", ""), Some(2));
+        assert_eq!(lines(&buffer), vec!["out 1", "E:err 1", "out 2"]);
+    }
+
+    #[test]
+    fn test_max_lines() {
+        let mut buffer = LogBuffer::new(Some(2));
+        buffer.append("1\n2\n3\n4\n5");
+        assert_eq!(buffer.first_line(), 2);
+        assert_eq!(buffer.end(), 5);
+        assert_eq!(lines(&buffer), vec!["3", "4", "5"]);
+        assert_eq!(
+            buffer.lines_from(0).collect::<Vec<_>>(),
+            vec![(2, "3"), (3, "4"), (4, "5")]
+        );
+        assert_eq!(buffer.lines_from(4).collect::<Vec<_>>(), vec![(4, "5")]);
+    }
+}
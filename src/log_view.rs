@@ -0,0 +1,561 @@
+use std::{iter::once, str::FromStr};
+
+use itertools::Either;
+use ratatui::{
+    style::{Color, Modifier, Style},
+    text::{Line, Span, Text},
+};
+use regex::{Regex, RegexSet};
+
+use crate::ansi;
+use crate::app::ScrollAnchor;
+use crate::config::{HighlightRule, Level, LogConfig};
+use crate::log_buffer::{line_bytes, raw_byte, LogBuffer};
+
+/// How to show the visible log lines and what to highlight in them.
+#[derive(Clone, Copy, Default)]
+pub struct LogDisplay<'a> {
+    pub mode: LogMode,
+    pub rules: Option<&'a LogHighlighter>,
+    pub search: Option<&'a Regex>,
+    pub current_line: Option<usize>, // index of the log line with the current search match
+}
+
+const DEFAULT_HIGHLIGHTS: &[(&str, Level)] = &[
+    (r"Traceback \(most recent call last\)", Level::Error),
+    (r"^\w*(Error|Exception)\b", Level::Error),
+    (
+        r"CUDA out of memory|OutOfMemoryError|oom-kill",
+        Level::Error,
+    ),
+    (r"slurmstepd: error", Level::Error),
+    (r"\bKilled\b", Level::Error),
+    (r"Segmentation fault|core dumped", Level::Error),
+    (r"DUE TO TIME LIMIT|CANCELLED AT", Level::Error),
+    (r"\bNaN\b", Level::Warning),
+    (r"(?i)\bwarn(ing)?\b|\w+Warning:", Level::Warning),
+];
+
+/// Classifies log lines by the configured error and warning patterns.
+pub struct LogHighlighter {
+    patterns: RegexSet, // matched in one pass, which matters for long logs
+    rules: Vec<(Level, Style)>,
+}
+
+impl LogHighlighter {
+    pub fn new(config: &LogConfig) -> Result<Self, String> {
+        let defaults = DEFAULT_HIGHLIGHTS
+            .iter()
+            .filter(|_| config.default_highlights)
+            .map(|(pattern, level)| HighlightRule {
+                pattern: pattern.to_string(),
+                level: *level,
+                color: None,
+            });
+
+        let mut rules = config
+            .highlight
+            .iter()
+            .cloned()
+            .chain(defaults)
+            .map(|rule| {
+                Regex::new(&rule.pattern)
+                    .map_err(|e| format!("invalid highlight pattern '{}': {}", rule.pattern, e))?;
+                let color = match rule.color.as_deref() {
+                    Some(c) => Color::from_str(c)
+                        .map_err(|_| format!("invalid highlight color '{}'", c))?,
+                    None if rule.level == Level::Error => Color::Red,
+                    None => Color::Yellow,
+                };
+                Ok((rule.pattern, rule.level, Style::default().fg(color)))
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        // errors win over warnings when a line matches both
+        rules.sort_by_key(|(_, level, _)| *level != Level::Error);
+        Ok(LogHighlighter {
+            patterns: RegexSet::new(rules.iter().map(|(pattern, _, _)| pattern))
+                .map_err(|e| e.to_string())?,
+            rules: rules
+                .into_iter()
+                .map(|(_, level, style)| (level, style))
+                .collect(),
+        })
+    }
+
+    pub fn classify(&self, line: &str) -> Option<(Level, Style)> {
+        let i = self.patterns.matches(line).into_iter().next()?;
+        Some(self.rules[i])
+    }
+}
+
+/// Progress reported by the latest progress bar in a log, e.g. from tqdm or Keras.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Progress {
+    pub percent: f64,
+    pub count: Option<String>, // e.g. `450/1000`
+    pub rate: Option<String>,
+    pub eta: Option<String>,
+}
+
+impl Progress {
+    /// Finds the latest progress bar among the last lines of a log, including the updates of the
+    /// line still being written.
+    pub fn find(log: &LogBuffer) -> Option<Progress> {
+        std::iter::once(log.unfinished())
+            .chain(log.range(0, log.complete_end()).rev())
+            .take(20)
+            .find_map(|l| {
+                l.rsplit('\r')
+                    .find_map(|update| Progress::parse(&ansi::strip(update)))
+            })
+    }
+
+    fn parse(line: &str) -> Option<Progress> {
+        lazy_static::lazy_static! {
+            // ` 45%|████▌     | 450/1000 [00:12<00:15, 36.2it/s]`
+            static ref TQDM: Regex = Regex::new(
+                r"(\d{1,3})%\|[^|]*\|\s*(\S+/\S+)\s*\[[\d:]+<([\d:?]+)(?:,\s*([^,\]]+))?"
+            ).unwrap();
+            static ref BAR: Regex = Regex::new(r"[\[|][█▏▎▍▌▋▊▉#=>. -]{5,}[\]|]|━{3,}").unwrap();
+            static ref PERCENT: Regex = Regex::new(r"(\d{1,3}(?:\.\d+)?) ?%").unwrap();
+            static ref COUNT: Regex = Regex::new(r"\b(\d+)/(\d+)\b").unwrap();
+            static ref ETA: Regex = Regex::new(r"(?i)\beta:? *(\d[\d:]*(?:\.\d+)?[hms]?)").unwrap();
+        }
+
+        if let Some(c) = TQDM.captures(line) {
+            return Some(Progress {
+                percent: c[1].parse().ok()?,
+                count: Some(c[2].to_owned()),
+                rate: c.get(4).map(|m| m.as_str().trim().to_owned()),
+                eta: Some(c[3].to_owned()).filter(|eta| eta != "?"),
+            });
+        }
+
+        let eta = ETA.captures(line).map(|c| c[1].to_owned());
+        if eta.is_none() && !BAR.is_match(line) {
+            return None;
+        }
+        let count = COUNT.captures(line);
+        let percent = match PERCENT.captures(line) {
+            Some(c) => c[1].parse().ok()?,
+            None => {
+                let c = count.as_ref()?;
+                let (done, total): (f64, f64) = (c[1].parse().ok()?, c[2].parse().ok()?);
+                if total == 0.0 {
+                    return None;
+                }
+                100.0 * done / total
+            }
+        };
+        Some(Progress {
+            percent: percent.min(100.0),
+            count: count.map(|c| c[0].to_owned()),
+            rate: None,
+            eta,
+        })
+    }
+}
+
+fn chunked_string(s: &str, first_chunk_size: usize, chunk_size: usize) -> Vec<&str> {
+    let stepped_indices = s
+        .char_indices()
+        .map(|(i, _)| i)
+        .enumerate()
+        .filter(|&(i, _)| {
+            if i > (first_chunk_size) {
+                chunk_size > 0 && (i - first_chunk_size).is_multiple_of(chunk_size)
+            } else {
+                i == 0 || i == first_chunk_size
+            }
+        })
+        .map(|(_, e)| e)
+        .collect::<Vec<_>>();
+    let windows = stepped_indices.windows(2).collect::<Vec<_>>();
+
+    let iter = windows.iter().map(|w| &s[w[0]..w[1]]);
+    let last_index = *stepped_indices.last().unwrap_or(&0);
+    iter.chain(once(&s[last_index..])).collect()
+}
+
+/// How log lines are shown, cycled with `x`.
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+pub enum LogMode {
+    /// As a terminal would show them.
+    #[default]
+    Text,
+    /// Control chars, escape sequences and invalid UTF-8 spelled out.
+    Escaped,
+    /// The bytes of each line as a hex dump.
+    Hex,
+}
+
+impl LogMode {
+    pub fn next(self) -> Self {
+        match self {
+            LogMode::Text => LogMode::Escaped,
+            LogMode::Escaped => LogMode::Hex,
+            LogMode::Hex => LogMode::Text,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            LogMode::Text => "text",
+            LogMode::Escaped => "escaped",
+            LogMode::Hex => "hex",
+        }
+    }
+}
+
+/// The chars of a line with control chars and invalid bytes replaced by dimmed escapes.
+fn escaped_cells(l: &str) -> Vec<(char, Style)> {
+    let escape_style = Style::default().add_modifier(Modifier::DIM);
+    let mut cells = Vec::with_capacity(l.len());
+    for c in l.chars() {
+        let escape = match (c, raw_byte(c)) {
+            (_, Some(b)) => format!("\\x{:02x}", b),
+            ('\x1b', _) => "\\e".to_owned(),
+            ('\r', _) => "\\r".to_owned(),
+            ('\t', _) => "\\t".to_owned(),
+            (c, _) if c.is_control() => format!("\\x{:02x}", c as u32),
+            (c, _) => {
+                cells.push((c, Style::default()));
+                continue;
+            }
+        };
+        cells.extend(escape.chars().map(|c| (c, escape_style)));
+    }
+    cells
+}
+
+/// A hex dump of the bytes of a line, as many bytes per row as fit in `cols` (up to 16).
+fn hex_lines(l: &str, cols: usize, is_current: bool) -> Vec<Line<'static>> {
+    let style = if is_current {
+        Style::default().bg(Color::DarkGray)
+    } else {
+        Style::default()
+    };
+    let dim = style.add_modifier(Modifier::DIM);
+    // 3 columns per byte for the hex and 1 for the ASCII part, plus a separating space
+    let bytes_per_row = (cols.saturating_sub(1) / 4).clamp(1, 16);
+    let bytes = line_bytes(l);
+    if bytes.is_empty() {
+        return vec![Line::default()];
+    }
+    bytes
+        .chunks(bytes_per_row)
+        .map(|row| {
+            let hex: String = row.iter().map(|b| format!("{:02x} ", b)).collect();
+            let ascii: String = row
+                .iter()
+                .map(|&b| {
+                    if b.is_ascii_graphic() || b == b' ' {
+                        b as char
+                    } else {
+                        '.'
+                    }
+                })
+                .collect();
+            Line::from(vec![
+                Span::styled(format!("{:width$}", hex, width = bytes_per_row * 3), style),
+                Span::styled(" ", style),
+                Span::styled(ascii, dim),
+            ])
+        })
+        .collect()
+}
+
+/// Renders a log line into its text (escape sequences applied, or spelled out) and styled spans,
+/// highlighting the search matches.
+fn styled_spans(l: &str, display: LogDisplay, is_current: bool) -> (String, Vec<Span<'static>>) {
+    let cells = match display.mode {
+        LogMode::Escaped => escaped_cells(l),
+        LogMode::Text | LogMode::Hex => ansi::parse(l),
+    };
+    let text: String = cells.iter().map(|(c, _)| c).collect();
+
+    let base = match display.rules.and_then(|r| r.classify(&ansi::strip(l))) {
+        Some((_, style)) => style,
+        None => Style::default(),
+    };
+    let mut styles: Vec<Style> = cells
+        .iter()
+        .map(|(_, style)| {
+            let style = base.patch(*style);
+            if is_current {
+                style.bg(Color::DarkGray)
+            } else {
+                style
+            }
+        })
+        .collect();
+
+    if let Some(re) = display.search {
+        let match_style = Style::default().fg(Color::Black).bg(Color::Yellow);
+        for m in re.find_iter(&text).filter(|m| !m.is_empty()) {
+            let start = text[..m.start()].chars().count();
+            let len = m.as_str().chars().count();
+            styles[start..start + len].fill(match_style);
+        }
+    }
+
+    let mut spans: Vec<Span<'static>> = Vec::new();
+    for ((c, _), style) in cells.iter().zip(styles) {
+        match spans.last_mut() {
+            Some(span) if span.style == style => span.content.to_mut().push(*c),
+            _ => spans.push(Span::styled(c.to_string(), style)),
+        }
+    }
+    if spans.is_empty() {
+        spans.push(Span::styled("", base));
+    }
+    (text, spans)
+}
+
+/// The spans covering chars `start..start + len` of the line made up of `spans`.
+fn slice_spans(spans: &[Span<'static>], start: usize, len: usize) -> Vec<Span<'static>> {
+    let mut sliced = Vec::new();
+    let mut pos = 0;
+    for span in spans {
+        let span_len = span.content.chars().count();
+        let from = start.max(pos);
+        let to = (start + len).min(pos + span_len);
+        if from < to {
+            let content: String = span
+                .content
+                .chars()
+                .skip(from - pos)
+                .take(to - from)
+                .collect();
+            sliced.push(Span::styled(content, span.style));
+        }
+        pos += span_len;
+    }
+    sliced
+}
+
+pub fn fit_text(
+    log: &LogBuffer,
+    lines: usize,
+    cols: usize,
+    anchor: ScrollAnchor,
+    offset: usize,
+    wrap: bool,
+    display: LogDisplay,
+) -> Text<'static> {
+    let iter = match anchor {
+        ScrollAnchor::Top => {
+            let start = log.first_line() + offset;
+            Either::Left(
+                log.range(start, usize::MAX)
+                    .enumerate()
+                    .map(move |(i, l)| (start + i, l)),
+            )
+        }
+        ScrollAnchor::Bottom => {
+            let end = log.end().saturating_sub(offset);
+            Either::Right(
+                log.range(0, end)
+                    .rev()
+                    .enumerate()
+                    .map(move |(i, l)| (end - 1 - i, l)),
+            )
+        }
+    };
+    let iter = iter
+        .flat_map(|(i, l)| {
+            let is_current = display.current_line == Some(i);
+            if display.mode == LogMode::Hex {
+                let lines = hex_lines(l, cols, is_current);
+                return match anchor {
+                    ScrollAnchor::Top => Either::Left(Either::Left(lines.into_iter())),
+                    ScrollAnchor::Bottom => Either::Left(Either::Right(lines.into_iter().rev())),
+                };
+            }
+            let (l, spans) = styled_spans(l, display, is_current);
+            let iter = if wrap {
+                let mut start = 0;
+                Either::Left(
+                    chunked_string(&l, cols, cols.saturating_sub(2))
+                        .into_iter()
+                        .enumerate()
+                        .map(|(i, chunk)| {
+                            let len = chunk.chars().count();
+                            let chunk_spans = slice_spans(&spans, start, len);
+                            start += len;
+                            if i == 0 {
+                                Line::from(chunk_spans)
+                            } else {
+                                Line::from(
+                                    once(Span::styled(
+                                        "↪ ",
+                                        Style::default().add_modifier(Modifier::DIM),
+                                    ))
+                                    .chain(chunk_spans)
+                                    .collect::<Vec<_>>(),
+                                )
+                            }
+                        })
+                        .collect::<Vec<_>>()
+                        .into_iter(),
+                )
+            } else {
+                match l.chars().nth(cols) {
+                    Some(_) => {
+                        // has more chars than cols
+                        let mut spans = slice_spans(&spans, 0, cols.saturating_sub(1));
+                        spans.push(Span::styled(
+                            "…",
+                            Style::default().add_modifier(Modifier::DIM),
+                        ));
+                        Either::Right(once(Line::from(spans)))
+                    }
+                    None => Either::Right(once(Line::from(spans))),
+                }
+            };
+            match anchor {
+                ScrollAnchor::Top => Either::Right(Either::Left(iter)),
+                ScrollAnchor::Bottom => Either::Right(Either::Right(iter.rev())),
+            }
+        })
+        .take(lines);
+
+    match anchor {
+        ScrollAnchor::Top => Text::from(iter.collect::<Vec<_>>()),
+        ScrollAnchor::Bottom => Text::from(
+            iter.collect::<Vec<_>>()
+                .into_iter()
+                .rev()
+                .collect::<Vec<_>>(),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::log_buffer::byte_char;
+
+    #[test]
+    fn test_chunked_string() {
+        // Divisible
+        let input = "abcdefghij";
+        let expected = vec!["abcd", "ef", "gh", "ij"];
+        assert_eq!(chunked_string(input, 4, 2), expected);
+
+        // Not divisible
+        let input = "123456789";
+        let expected = vec!["1234", "56", "78", "9"];
+        assert_eq!(chunked_string(input, 4, 2), expected);
+
+        // Smaller
+        let input = "abc";
+        let expected = vec!["abc"];
+        assert_eq!(chunked_string(input, 4, 2), expected);
+
+        // Smaller
+        let input = "abcde";
+        let expected = vec!["abcd", "e"];
+        assert_eq!(chunked_string(input, 4, 2), expected);
+
+        // Empty
+        let input = "";
+        let expected: Vec<&str> = vec![""];
+        assert_eq!(chunked_string(input, 4, 2), expected);
+
+        let input = "123456789";
+        let expected = vec!["1234", "56789"];
+        assert_eq!(chunked_string(input, 4, 0), expected);
+
+        let input = "123456789";
+        let expected = vec!["12", "34", "56", "78", "9"];
+        assert_eq!(chunked_string(input, 0, 2), expected);
+
+        let input = "123456789";
+        let expected = vec!["123456789"];
+        assert_eq!(chunked_string(input, 0, 0), expected);
+    }
+
+    #[test]
+    fn test_slice_spans() {
+        let re = Regex::new("err").unwrap();
+        let display = LogDisplay {
+            search: Some(&re),
+            ..Default::default()
+        };
+        let (_, spans) = styled_spans("an error here", display, false);
+        assert_eq!(spans.len(), 3);
+
+        let sliced = slice_spans(&spans, 2, 6);
+        let contents: Vec<_> = sliced.iter().map(|s| s.content.as_ref()).collect();
+        assert_eq!(contents, vec![" ", "err", "or"]);
+
+        // escape sequences are applied before searching
+        let (text, spans) = styled_spans("\x1b[32man e\x1b[0mrror", display, false);
+        assert_eq!(text, "an error");
+        let contents: Vec<_> = spans.iter().map(|s| s.content.as_ref()).collect();
+        assert_eq!(contents, vec!["an ", "err", "or"]);
+        assert_eq!(spans[0].style.fg, Some(Color::Green));
+    }
+
+    #[test]
+    fn test_escaped_and_hex() {
+        let l = format!("\x1b[1mok\x1b[0m\t{}", byte_char(0xff));
+        let text: String = escaped_cells(&l).iter().map(|(c, _)| c).collect();
+        assert_eq!(text, "\\e[1mok\\e[0m\\t\\xff");
+
+        let lines = hex_lines("abc", 13, false);
+        let contents: Vec<_> = lines[0].spans.iter().map(|s| s.content.as_ref()).collect();
+        assert_eq!(contents, vec!["61 62 63 ", " ", "abc"]);
+        assert_eq!(hex_lines("abcde", 13, false).len(), 2);
+    }
+
+    #[test]
+    fn test_progress() {
+        let tqdm = "epoch 1:  45%|████▌     | 450/1000 [00:12<00:15, 36.2it/s, loss=0.3]";
+        assert_eq!(
+            Progress::parse(tqdm),
+            Some(Progress {
+                percent: 45.0,
+                count: Some("450/1000".to_owned()),
+                rate: Some("36.2it/s".to_owned()),
+                eta: Some("00:15".to_owned()),
+            })
+        );
+
+        let keras = "250/1000 [======>.......................] - ETA: 1:05 - loss: 0.41";
+        let progress = Progress::parse(keras).unwrap();
+        assert_eq!(progress.percent, 25.0);
+        assert_eq!(progress.eta.as_deref(), Some("1:05"));
+
+        assert_eq!(Progress::parse("accuracy: 95% on 100/120 samples"), None);
+        let mut log = LogBuffer::new(None);
+        log.append("step 1\r 10%|#   | 1/10 [00:01<00:09]\r 20%|##  | 2/10 [00:02<00:08]\rtrunc");
+        assert_eq!(Progress::find(&log).map(|p| p.percent), Some(20.0));
+    }
+
+    #[test]
+    fn test_default_highlights() {
+        let highlighter = LogHighlighter::new(&LogConfig::default()).unwrap();
+        let level = |l| highlighter.classify(l).map(|(level, _)| level);
+
+        assert_eq!(
+            level("Traceback (most recent call last):"),
+            Some(Level::Error)
+        );
+        assert_eq!(
+            level("RuntimeError: CUDA out of memory. Tried to allocate"),
+            Some(Level::Error)
+        );
+        assert_eq!(
+            level("slurmstepd: error: *** JOB 42 CANCELLED AT 2024-01-01 ***"),
+            Some(Level::Error)
+        );
+        assert_eq!(level("loss: NaN"), Some(Level::Warning));
+        assert_eq!(
+            level("UserWarning: this is deprecated"),
+            Some(Level::Warning)
+        );
+        assert_eq!(level("epoch 3: loss 0.12"), None);
+    }
+}
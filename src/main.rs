@@ -1,25 +1,44 @@
+mod ansi;
 mod app;
+mod config;
+mod export;
 mod file_watcher;
+mod filter;
+mod fuzzy_finder;
+mod grouping;
+mod headless;
 mod job_watcher;
+mod log_buffer;
+mod log_view;
+mod notifications;
 mod squeue_args;
+mod submit;
 
 use app::App;
+use clap::error::ErrorKind;
 use clap::CommandFactory;
 use clap::Parser;
 use clap::Subcommand;
 use clap_complete::{generate, Shell};
-use crossbeam::channel::{unbounded, Sender};
+use config::Config;
+use crossbeam::channel::{bounded, unbounded, Receiver, Sender};
 use crossterm::{
     event::{self, DisableMouseCapture, EnableMouseCapture, Event},
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
+use export::JobsFormat;
+use filter::Filter;
+use grouping::GroupKey;
+use job_watcher::JobLister;
+use log_view::LogHighlighter;
+use notifications::Notifier;
 use ratatui::{
     backend::{Backend, CrosstermBackend},
     Terminal,
 };
 use squeue_args::SqueueArgs;
-use std::{io, thread};
+use std::{io, path::PathBuf, thread, time::Duration};
 
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
@@ -32,6 +51,19 @@ struct Cli {
     #[arg(long, value_name = "SECONDS", default_value_t = 2)]
     file_refresh: u64,
 
+    /// Group the job list by these fields, outermost first (e.g. `user,partition`).
+    #[arg(long, value_name = "FIELDS", value_delimiter = ',')]
+    group_by: Vec<GroupKey>,
+
+    /// Only show jobs matching this filter expression (e.g. `state:R partition:gpu time>2h`).
+    #[arg(long, value_name = "EXPR")]
+    filter: Option<String>,
+
+    /// Write the job list with `--filter` and `--group-by` applied to this file and exit, as JSON,
+    /// CSV or TSV depending on its extension.
+    #[arg(long, value_name = "PATH")]
+    export: Option<PathBuf>,
+
     /// squeue arguments
     #[command(flatten)]
     squeue_args: SqueueArgs,
@@ -47,17 +79,113 @@ enum CliCommand {
         /// The shell to generate completion for.
         shell: Shell,
     },
+    /// Print the jobs matching the squeue arguments and `--filter` to stdout, without the TUI.
+    List {
+        /// The output format; JSON prints one object per line.
+        #[arg(long, value_enum, default_value = "json")]
+        format: JobsFormat,
+    },
+    /// Wait until a job has left the queue and exit with its exit code.
+    Wait {
+        /// The job id, or an array job id to wait for all of its tasks.
+        job_id: String,
+    },
+    /// Print the end of a job's log and follow it until the job has left the queue.
+    Tail {
+        /// The job id; an array task is given as `<array id>_<task id>`.
+        job_id: String,
+
+        /// Follow the stderr log instead of stdout.
+        #[arg(long)]
+        stderr: bool,
+
+        /// How many lines of the existing log to print first.
+        #[arg(short = 'n', long, value_name = "LINES", default_value_t = 10)]
+        lines: usize,
+    },
 }
 
 fn main() -> Result<(), io::Error> {
     let args = Cli::parse();
+    if let Some(CliCommand::Completion { shell }) = &args.command {
+        let cmd = &mut Cli::command();
+        generate(*shell, cmd, cmd.get_name().to_string(), &mut io::stdout());
+        return Ok(());
+    }
+    if args.export.is_some() && args.command.is_some() {
+        Cli::command()
+            .error(
+                ErrorKind::ArgumentConflict,
+                "--export can't be used with a subcommand",
+            )
+            .exit()
+    }
+
+    let config = config::load()?;
+    let log_highlighter = LogHighlighter::new(&config.log).map_err(|e| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("invalid config.toml: {}", e),
+        )
+    })?;
+    let notifier = Notifier::new(config.notifications.clone(), &config.hooks).map_err(|e| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("invalid config.toml: {}", e),
+        )
+    })?;
+    let filter = args.filter.as_deref().map(|f| {
+        Filter::parse(f, &config.saved_filters).unwrap_or_else(|e| {
+            Cli::command()
+                .error(
+                    ErrorKind::ValueValidation,
+                    format!("invalid --filter: {}", e),
+                )
+                .exit()
+        })
+    });
+
+    if let Some(path) = &args.export {
+        let format = JobsFormat::from_path(path).unwrap_or_else(|| {
+            Cli::command()
+                .error(
+                    ErrorKind::ValueValidation,
+                    "--export needs a .json, .csv or .tsv file",
+                )
+                .exit()
+        });
+        let mut lister = JobLister::new(args.squeue_args.to_vec(), config.log.search);
+        return headless::export(&mut lister, filter.as_ref(), &args.group_by, path, format);
+    }
+
+    let slurm_refresh = Duration::from_secs(args.slurm_refresh);
+    let file_refresh = Duration::from_secs(args.file_refresh);
     match args.command {
-        Some(CliCommand::Completion { shell }) => {
-            let cmd = &mut Cli::command();
-            generate(shell, cmd, cmd.get_name().to_string(), &mut io::stdout());
-            return Ok(());
+        Some(CliCommand::List { format }) => {
+            let mut lister = JobLister::new(args.squeue_args.to_vec(), config.log.search);
+            return headless::list(&mut lister, filter.as_ref(), format);
+        }
+        Some(CliCommand::Wait { job_id }) => {
+            let code = headless::wait(&job_id, slurm_refresh)?;
+            std::process::exit(code);
         }
-        None => {}
+        Some(CliCommand::Tail {
+            job_id,
+            stderr,
+            lines,
+        }) => {
+            let intervals = (slurm_refresh, file_refresh);
+            let remote = config.log.remote.as_ref();
+            return headless::tail(
+                &job_id,
+                stderr,
+                lines,
+                config.log.search.clone(),
+                remote,
+                intervals,
+            );
+        }
+        _ => {}
     }
 
     // setup terminal
@@ -66,7 +194,14 @@ fn main() -> Result<(), io::Error> {
     execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
-    run_app(&mut terminal, args)?;
+    run_app(
+        &mut terminal,
+        args,
+        filter,
+        config,
+        log_highlighter,
+        notifier,
+    )?;
 
     // restore terminal
     disable_raw_mode()?;
@@ -80,20 +215,44 @@ fn main() -> Result<(), io::Error> {
     Ok(())
 }
 
-fn input_loop(tx: Sender<std::io::Result<Event>>) {
+/// Reads terminal events. A message on `pause` stops reading until the next one, while the app
+/// hands the terminal to another program.
+fn input_loop(tx: Sender<std::io::Result<Event>>, pause: Receiver<()>) {
     loop {
-        tx.send(event::read()).unwrap();
+        if pause.try_recv().is_ok() {
+            pause.recv().unwrap();
+        }
+        match event::poll(Duration::from_millis(100)) {
+            Ok(true) => tx.send(event::read()).unwrap(),
+            Ok(false) => {}
+            Err(e) => tx.send(Err(e)).unwrap(),
+        }
     }
 }
 
-fn run_app<B: Backend>(terminal: &mut Terminal<B>, args: Cli) -> io::Result<()> {
+fn run_app<B: Backend>(
+    terminal: &mut Terminal<B>,
+    args: Cli,
+    filter: Option<Filter>,
+    config: Config,
+    log_highlighter: LogHighlighter,
+    notifier: Notifier,
+) -> io::Result<()> {
     let (input_tx, input_rx) = unbounded();
+    // unbuffered, so that the app knows that the input thread has paused when the send returns
+    let (pause_tx, pause_rx) = bounded(0);
     let mut app = App::new(
         input_rx,
+        pause_tx,
         args.slurm_refresh,
         args.file_refresh,
         args.squeue_args.to_vec(),
+        args.group_by,
+        filter,
+        config,
+        log_highlighter,
+        notifier,
     );
-    thread::spawn(move || input_loop(input_tx));
+    thread::spawn(move || input_loop(input_tx, pause_rx));
     app.run(terminal)
 }
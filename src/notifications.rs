@@ -0,0 +1,540 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    fs::OpenOptions,
+    io::{self, Write},
+    path::PathBuf,
+    process::{Command, Output, Stdio},
+    thread,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use regex::Regex;
+use serde::Deserialize;
+
+use crate::app::Job;
+use crate::config::{self, HookConfig, NotificationConfig, OscNotification};
+
+/// A change of a job's state between two polls of the queue.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobEvent {
+    /// Pending to running.
+    Started,
+    /// Gone from the queue while running or completing, without failing.
+    Finished,
+    Failed,
+    Timeout,
+    OutOfMemory,
+}
+
+impl JobEvent {
+    pub const ALL: [JobEvent; 5] = [
+        JobEvent::Started,
+        JobEvent::Finished,
+        JobEvent::Failed,
+        JobEvent::Timeout,
+        JobEvent::OutOfMemory,
+    ];
+
+    /// The name used in the config and passed to hook commands.
+    pub fn name(self) -> &'static str {
+        match self {
+            JobEvent::Started => "started",
+            JobEvent::Finished => "finished",
+            JobEvent::Failed => "failed",
+            JobEvent::Timeout => "timeout",
+            JobEvent::OutOfMemory => "out_of_memory",
+        }
+    }
+}
+
+impl fmt::Display for JobEvent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            JobEvent::Started => "started",
+            JobEvent::Finished => "finished",
+            JobEvent::Failed => "failed",
+            JobEvent::Timeout => "timed out",
+            JobEvent::OutOfMemory => "ran out of memory",
+        })
+    }
+}
+
+impl JobEvent {
+    /// The event of a job that ended in `state` without success.
+    fn failure(state: &str) -> Option<JobEvent> {
+        match state {
+            "FAILED" | "NODE_FAIL" => Some(JobEvent::Failed),
+            "TIMEOUT" => Some(JobEvent::Timeout),
+            "OUT_OF_MEMORY" => Some(JobEvent::OutOfMemory),
+            _ => None,
+        }
+    }
+
+    /// The event of a job that left the queue, by the state it ended in if it's known.
+    fn ended(state: Option<&str>) -> JobEvent {
+        state
+            .and_then(JobEvent::failure)
+            .unwrap_or(JobEvent::Finished)
+    }
+}
+
+/// The events between the jobs of the previous and the current poll, with the job as last seen,
+/// and the running jobs that left the queue since, whose event is told by [`ended_event`]. Each
+/// poll comes with the generation of the squeue arguments it was listed with: polls of different
+/// generations aren't compared, since a job missing from one may just not match its arguments.
+pub fn job_events<'a>(
+    (previous_generation, previous): (u64, &'a [Job]),
+    (generation, current): (u64, &'a [Job]),
+) -> (Vec<(JobEvent, &'a Job)>, Vec<&'a Job>) {
+    if previous_generation != generation {
+        return (Vec::new(), Vec::new());
+    }
+    let previous_states: HashMap<String, &str> = previous
+        .iter()
+        .map(|j| (j.id(), j.state.as_str()))
+        .collect();
+    let mut events = Vec::new();
+    for job in current {
+        // a job that isn't in the previous poll may have been submitted since
+        let Some(&before) = previous_states.get(&job.id()) else {
+            continue;
+        };
+        let event = match (before, job.state.as_str()) {
+            (before, now) if before == now => None,
+            ("PENDING", "RUNNING") => Some(JobEvent::Started),
+            // a completing job may have failed, which sacct tells once it left the queue
+            (_, "COMPLETING") => None,
+            (_, now) => JobEvent::failure(now),
+        };
+        events.extend(event.map(|e| (e, job)));
+    }
+
+    let current_ids: Vec<String> = current.iter().map(Job::id).collect();
+    let ended = previous
+        .iter()
+        .filter(|j| {
+            matches!(j.state.as_str(), "RUNNING" | "COMPLETING") && !current_ids.contains(&j.id())
+        })
+        .collect();
+    (events, ended)
+}
+
+/// The event of a running or completing job that left the queue, by the state `sacct` reports it
+/// ended in, or [`JobEvent::Finished`] if it doesn't know the job (yet).
+pub fn ended_event(job: &Job) -> JobEvent {
+    let output = Command::new("sacct")
+        .args([
+            "--noheader",
+            "--allocations",
+            "--parsable2",
+            "--format=State",
+            "--jobs",
+            &job.id(),
+        ])
+        .output()
+        .ok()
+        .filter(|o| o.status.success())
+        .map(|o| String::from_utf8_lossy(&o.stdout).into_owned());
+    JobEvent::ended(output.as_deref().and_then(final_state))
+}
+
+/// The state in `sacct --format=State` output, like `CANCELLED` for `CANCELLED by 1000`.
+fn final_state(output: &str) -> Option<&str> {
+    output
+        .lines()
+        .next()?
+        .split(['|', ' '])
+        .next()
+        .filter(|s| !s.is_empty())
+}
+
+/// Tells about job events with the configured terminal escape sequences and notification command,
+/// and runs the hooks for them.
+pub struct Notifier {
+    config: NotificationConfig,
+    hooks: Vec<Hook>,
+    /// Where the output of the commands goes.
+    log: Option<PathBuf>,
+}
+
+/// A command run for the events of jobs matching its filters, see [`HookConfig`].
+struct Hook {
+    on: Vec<JobEvent>,
+    name: Option<Regex>,
+    partitions: Vec<String>,
+    command: String,
+}
+
+impl Hook {
+    fn matches(&self, event: JobEvent, job: &Job) -> bool {
+        self.on.contains(&event)
+            && self
+                .name
+                .as_ref()
+                .is_none_or(|name| name.is_match(&job.name))
+            && (self.partitions.is_empty() || self.partitions.contains(&job.partition))
+    }
+}
+
+impl Notifier {
+    pub fn new(config: NotificationConfig, hooks: &[HookConfig]) -> Result<Self, String> {
+        let hooks = hooks
+            .iter()
+            .map(|hook| {
+                let name = hook
+                    .name
+                    .as_deref()
+                    .map(Regex::new)
+                    .transpose()
+                    .map_err(|e| format!("invalid hook name pattern: {}", e))?;
+                Ok(Hook {
+                    on: hook.on.clone(),
+                    name,
+                    partitions: hook.partitions.clone(),
+                    command: hook.command.clone(),
+                })
+            })
+            .collect::<Result<_, String>>()?;
+        Ok(Notifier {
+            config,
+            hooks,
+            log: config::hook_log_path(),
+        })
+    }
+
+    /// Tells about `event` if it's one of the configured events, and returns whether it was. Runs
+    /// the hooks for it either way.
+    pub fn notify(&self, event: JobEvent, job: &Job) -> bool {
+        for hook in self.hooks.iter().filter(|h| h.matches(event, job)) {
+            run_hook(&hook.command, event, job, self.log.clone());
+        }
+        if !self.config.events.contains(&event) {
+            return false;
+        }
+        let title = format!("turf: job {} {}", job.id(), event);
+        let mut sequence = String::new();
+        if self.config.bell {
+            sequence.push('\x07');
+        }
+        match self.config.osc {
+            Some(OscNotification::Osc9) => {
+                sequence += &tmux_passthrough(&format!("\x1b]9;{}: {}\x07", title, job.name))
+            }
+            Some(OscNotification::Osc777) => {
+                sequence +=
+                    &tmux_passthrough(&format!("\x1b]777;notify;{};{}\x07", title, job.name))
+            }
+            None => {}
+        }
+        if !sequence.is_empty() {
+            let mut stdout = io::stdout();
+            let _ = stdout
+                .write_all(sequence.as_bytes())
+                .and_then(|_| stdout.flush());
+        }
+        if let Some(command) = &self.config.command {
+            run_hook(command, event, job, self.log.clone());
+        }
+        true
+    }
+}
+
+/// Runs `command` with `sh -c` in the background, with the job in `TURF_*` environment variables,
+/// and appends its output to `log`.
+fn run_hook(command: &str, event: JobEvent, job: &Job, log: Option<PathBuf>) {
+    let path = |p: &Option<std::path::PathBuf>| {
+        p.as_ref()
+            .map(|p| p.as_os_str().to_owned())
+            .unwrap_or_default()
+    };
+    let child = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .env("TURF_EVENT", event.name())
+        .env("TURF_JOB_ID", job.id())
+        .env("TURF_JOB_NAME", &job.name)
+        .env("TURF_JOB_STATE", &job.state)
+        .env("TURF_JOB_USER", &job.user)
+        .env("TURF_JOB_PARTITION", &job.partition)
+        .env("TURF_JOB_NODES", &job.nodelist)
+        .env("TURF_JOB_STDOUT", path(&job.stdout))
+        .env("TURF_JOB_STDERR", path(&job.stderr))
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn();
+    let header = format!(
+        "{} {} {}: {}",
+        utc_timestamp(SystemTime::now()),
+        event.name(),
+        job.id(),
+        command
+    );
+    thread::spawn(move || {
+        let entry = log_entry(&header, child.and_then(|child| child.wait_with_output()));
+        if let Some(log) = log {
+            // a single write, so that the entries of hooks finishing together aren't mixed up
+            let _ = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(log)
+                .and_then(|mut f| f.write_all(entry.as_bytes()));
+        }
+    });
+}
+
+fn log_entry(header: &str, output: io::Result<Output>) -> String {
+    let mut entry = header.to_owned() + "\n";
+    match output {
+        Ok(output) => {
+            for stream in [&output.stdout, &output.stderr] {
+                let text = String::from_utf8_lossy(stream);
+                entry += &text;
+                if !text.is_empty() && !text.ends_with('\n') {
+                    entry.push('\n');
+                }
+            }
+            if !output.status.success() {
+                entry += &format!("{}\n", output.status);
+            }
+        }
+        Err(e) => entry += &format!("failed to run: {}\n", e),
+    }
+    entry
+}
+
+/// `time` as `YYYY-MM-DD HH:MM:SS UTC`.
+fn utc_timestamp(time: SystemTime) -> String {
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let (days, secs) = (secs / 86400, secs % 86400);
+    // the civil date from the days since 1970-01-01, see
+    // https://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let z = days as i64 + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + (month <= 2) as i64;
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02} UTC",
+        year,
+        month,
+        day,
+        secs / 3600,
+        secs / 60 % 60,
+        secs % 60
+    )
+}
+
+/// Wraps an escape sequence for tmux to pass it on to the terminal, when running in tmux.
+fn tmux_passthrough(sequence: &str) -> String {
+    if std::env::var_os("TMUX").is_some() {
+        format!("\x1bPtmux;{}\x1b\\", sequence.replace('\x1b', "\x1b\x1b"))
+    } else {
+        sequence.to_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn job(id: &str, state: &str) -> Job {
+        Job {
+            job_id: id.to_owned(),
+            array_id: id.to_owned(),
+            name: "train".to_owned(),
+            state: state.to_owned(),
+            user: "alice".to_owned(),
+            account: "proj".to_owned(),
+            partition: "gpu".to_owned(),
+            ..Job::default()
+        }
+    }
+
+    #[test]
+    fn test_job_events() {
+        let previous = [
+            job("1", "PENDING"),
+            job("2", "RUNNING"),
+            job("3", "RUNNING"),
+            job("4", "RUNNING"),
+            job("5", "COMPLETING"),
+            job("6", "PENDING"),
+            job("7", "RUNNING"),
+        ];
+        let current = [
+            job("1", "RUNNING"),
+            job("2", "COMPLETING"),
+            job("3", "TIMEOUT"),
+            job("7", "RUNNING"),
+            job("8", "RUNNING"),
+        ];
+        let (events, ended) = job_events((0, &previous), (0, &current));
+        let events: Vec<(JobEvent, String)> =
+            events.into_iter().map(|(e, j)| (e, j.id())).collect();
+        assert_eq!(
+            events,
+            vec![
+                (JobEvent::Started, "1".to_owned()),
+                (JobEvent::Timeout, "3".to_owned()),
+            ]
+        );
+        assert_eq!(ended.iter().map(|j| j.id()).collect::<Vec<_>>(), ["4", "5"]);
+        let (events, ended) = job_events((0, &[]), (0, &current));
+        assert!(events.is_empty() && ended.is_empty());
+    }
+
+    #[test]
+    fn test_job_events_args_changed() {
+        // entering array job 9's view lists only its tasks, and leaving it lists all jobs again
+        let all = [
+            job("1", "RUNNING"),
+            job("9_1", "RUNNING"),
+            job("9_2", "PENDING"),
+        ];
+        let array = [job("9_1", "RUNNING"), job("9_2", "RUNNING")];
+        let (events, ended) = job_events((0, &all), (1, &array));
+        assert!(events.is_empty() && ended.is_empty());
+        let (events, ended) = job_events((1, &array), (2, &all));
+        assert!(events.is_empty() && ended.is_empty());
+        // a poll with the same arguments again is compared
+        let (events, ended) = job_events((1, &array), (1, &array[1..]));
+        assert!(events.is_empty());
+        assert_eq!(ended.iter().map(|j| j.id()).collect::<Vec<_>>(), ["9_1"]);
+    }
+
+    #[test]
+    fn test_failed_through_completing() {
+        // a failed job is usually listed as completing before it leaves the queue
+        let polls = [
+            vec![job("1", "RUNNING")],
+            vec![job("1", "COMPLETING")],
+            vec![],
+        ];
+        let (events, ended) = job_events((0, &polls[0]), (0, &polls[1]));
+        assert!(events.is_empty() && ended.is_empty());
+        let (events, ended) = job_events((0, &polls[1]), (0, &polls[2]));
+        assert!(events.is_empty());
+        assert_eq!(ended.iter().map(|j| j.id()).collect::<Vec<_>>(), ["1"]);
+
+        let event = JobEvent::ended(final_state("FAILED\n"));
+        assert_eq!(event, JobEvent::Failed);
+        let hook = |on| Hook {
+            on: vec![on],
+            name: None,
+            partitions: Vec::new(),
+            command: String::new(),
+        };
+        assert!(hook(JobEvent::Failed).matches(event, ended[0]));
+        assert!(!hook(JobEvent::Finished).matches(event, ended[0]));
+        assert_eq!(
+            JobEvent::ended(final_state("COMPLETED\n")),
+            JobEvent::Finished
+        );
+        assert_eq!(JobEvent::ended(None), JobEvent::Finished);
+    }
+
+    #[test]
+    fn test_final_state() {
+        assert_eq!(
+            final_state("OUT_OF_MEMORY\n").and_then(JobEvent::failure),
+            Some(JobEvent::OutOfMemory)
+        );
+        assert_eq!(final_state("CANCELLED by 1000\n"), Some("CANCELLED"));
+        assert_eq!(
+            final_state("FAILED|3:0\n").and_then(JobEvent::failure),
+            Some(JobEvent::Failed)
+        );
+        assert_eq!(final_state("COMPLETED\n").and_then(JobEvent::failure), None);
+        assert_eq!(final_state(""), None);
+    }
+
+    #[test]
+    fn test_hook_matches() {
+        let hook = Hook {
+            on: vec![JobEvent::Failed, JobEvent::Timeout],
+            name: Some(Regex::new("^tr").unwrap()),
+            partitions: vec!["gpu".to_owned()],
+            command: String::new(),
+        };
+        let j = job("1", "FAILED");
+        assert!(hook.matches(JobEvent::Failed, &j));
+        assert!(!hook.matches(JobEvent::Finished, &j));
+        assert!(!hook.matches(
+            JobEvent::Failed,
+            &Job {
+                partition: "cpu".to_owned(),
+                ..job("1", "FAILED")
+            }
+        ));
+        assert!(!hook.matches(
+            JobEvent::Failed,
+            &Job {
+                name: "eval".to_owned(),
+                ..job("1", "FAILED")
+            }
+        ));
+    }
+
+    #[test]
+    fn test_run_hook() {
+        let log = std::env::temp_dir().join(format!("turf-hooks-{}.log", std::process::id()));
+        let _ = std::fs::remove_file(&log);
+        let j = Job {
+            array_id: "9".to_owned(),
+            array_step: Some("2".to_owned()),
+            nodelist: "gpu[01-02]".to_owned(),
+            stdout: Some(PathBuf::from("/logs/train 9_2.out")),
+            ..job("11", "OUT_OF_MEMORY")
+        };
+        let command = "env | grep ^TURF_ | sort; echo oops >&2; exit 3";
+        run_hook(command, JobEvent::OutOfMemory, &j, Some(log.clone()));
+
+        let mut entry = String::new();
+        for _ in 0..100 {
+            entry = std::fs::read_to_string(&log).unwrap_or_default();
+            if entry.ends_with("exit status: 3\n") {
+                break;
+            }
+            thread::sleep(std::time::Duration::from_millis(50));
+        }
+        std::fs::remove_file(&log).unwrap();
+        let (header, output) = entry.split_once('\n').unwrap();
+        assert!(header
+            .ends_with(" UTC out_of_memory 9_2: env | grep ^TURF_ | sort; echo oops >&2; exit 3"));
+        assert_eq!(
+            output,
+            "TURF_EVENT=out_of_memory\n\
+             TURF_JOB_ID=9_2\n\
+             TURF_JOB_NAME=train\n\
+             TURF_JOB_NODES=gpu[01-02]\n\
+             TURF_JOB_PARTITION=gpu\n\
+             TURF_JOB_STATE=OUT_OF_MEMORY\n\
+             TURF_JOB_STDERR=\n\
+             TURF_JOB_STDOUT=/logs/train 9_2.out\n\
+             TURF_JOB_USER=alice\n\
+             oops\n\
+             exit status: 3\n"
+        );
+    }
+
+    #[test]
+    fn test_utc_timestamp() {
+        let time = |secs| UNIX_EPOCH + std::time::Duration::from_secs(secs);
+        assert_eq!(utc_timestamp(time(0)), "1970-01-01 00:00:00 UTC");
+        assert_eq!(utc_timestamp(time(951_782_400)), "2000-02-29 00:00:00 UTC");
+        assert_eq!(
+            utc_timestamp(time(1_792_327_384)),
+            "2026-10-18 12:43:04 UTC"
+        );
+    }
+}
@@ -0,0 +1,413 @@
+use std::{path::Path, process::Command};
+
+use crate::file_watcher::shell_quote;
+use crate::job_watcher::scontrol_fields;
+
+/// A field of the submit form, in the order shown.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum SubmitField {
+    Script,
+    Args,
+    Name,
+    Partition,
+    Account,
+    Time,
+    Nodes,
+    Cpus,
+    Mem,
+    Gpus,
+    WorkDir,
+    Output,
+    Error,
+    Extra,
+}
+
+impl SubmitField {
+    pub const ALL: [SubmitField; 14] = [
+        SubmitField::Script,
+        SubmitField::Args,
+        SubmitField::Name,
+        SubmitField::Partition,
+        SubmitField::Account,
+        SubmitField::Time,
+        SubmitField::Nodes,
+        SubmitField::Cpus,
+        SubmitField::Mem,
+        SubmitField::Gpus,
+        SubmitField::WorkDir,
+        SubmitField::Output,
+        SubmitField::Error,
+        SubmitField::Extra,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            SubmitField::Script => "script",
+            SubmitField::Args => "arguments",
+            SubmitField::Name => "name",
+            SubmitField::Partition => "partition",
+            SubmitField::Account => "account",
+            SubmitField::Time => "time",
+            SubmitField::Nodes => "nodes",
+            SubmitField::Cpus => "cpus/task",
+            SubmitField::Mem => "mem",
+            SubmitField::Gpus => "gpus",
+            SubmitField::WorkDir => "workdir",
+            SubmitField::Output => "output",
+            SubmitField::Error => "error",
+            SubmitField::Extra => "options",
+        }
+    }
+
+    /// The sbatch option the field is passed as; the script, its arguments and the extra options
+    /// are passed as is.
+    fn option(self) -> Option<&'static str> {
+        match self {
+            SubmitField::Script | SubmitField::Args | SubmitField::Extra => None,
+            SubmitField::Name => Some("--job-name"),
+            SubmitField::Partition => Some("--partition"),
+            SubmitField::Account => Some("--account"),
+            SubmitField::Time => Some("--time"),
+            SubmitField::Nodes => Some("--nodes"),
+            SubmitField::Cpus => Some("--cpus-per-task"),
+            SubmitField::Mem => Some("--mem"),
+            SubmitField::Gpus => Some("--gpus"),
+            SubmitField::WorkDir => Some("--chdir"),
+            SubmitField::Output => Some("--output"),
+            SubmitField::Error => Some("--error"),
+        }
+    }
+}
+
+/// The sbatch options entered in the submit form. Empty fields are left to the script's
+/// `#SBATCH` lines and sbatch's defaults.
+#[derive(Clone, Default)]
+pub struct SubmitForm {
+    values: [String; SubmitField::ALL.len()],
+    /// The index of the field being edited.
+    pub focus: usize,
+    pub error: Option<String>,
+}
+
+impl SubmitForm {
+    pub fn value(&self, field: SubmitField) -> &str {
+        &self.values[field as usize]
+    }
+
+    pub fn set(&mut self, field: SubmitField, value: String) {
+        self.values[field as usize] = value;
+    }
+
+    pub fn focused(&mut self) -> &mut String {
+        &mut self.values[self.focus]
+    }
+
+    /// The sbatch command line to run with `sh -c`. The extra options and the script's arguments
+    /// are passed unquoted, so they can be quoted like on the command line.
+    pub fn command_line(&self) -> Result<String, String> {
+        let script = self.value(SubmitField::Script).trim();
+        if script.is_empty() {
+            return Err("no script given".to_string());
+        }
+        let mut command = "sbatch --parsable".to_string();
+        for field in SubmitField::ALL {
+            let value = self.value(field).trim();
+            match field.option() {
+                Some(option) if !value.is_empty() => {
+                    command += &format!(" {}={}", option, shell_quote(value));
+                }
+                _ => {}
+            }
+        }
+        let extra = self.value(SubmitField::Extra).trim();
+        if !extra.is_empty() {
+            command += " ";
+            command += extra;
+        }
+        command += " ";
+        command += &shell_quote(script);
+        let args = self.value(SubmitField::Args).trim();
+        if !args.is_empty() {
+            command += " ";
+            command += args;
+        }
+        Ok(command)
+    }
+}
+
+/// The form to submit the job with the id again, see [`resubmit_form`].
+pub fn job_form(job_id: &str) -> Result<SubmitForm, String> {
+    let output = Command::new("scontrol")
+        .args(["--oneliner", "show", "job", job_id])
+        .output()
+        .map_err(|e| format!("failed to run scontrol: {}", e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "scontrol failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    resubmit_form(
+        String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .next()
+            .unwrap_or_default(),
+    )
+}
+
+/// The form to submit a job again as it was submitted, from `scontrol --oneliner show job`. Its
+/// ids in the output files are turned back into `%j`, `%A` and `%a`, so the new job gets its own
+/// files, and an array task is resubmitted by itself.
+pub fn resubmit_form(scontrol: &str) -> Result<SubmitForm, String> {
+    let fields = scontrol_fields(scontrol);
+    let field = |key: &str| {
+        fields
+            .iter()
+            .find(|(k, _)| *k == key)
+            .map(|(_, v)| *v)
+            .filter(|v| !v.is_empty() && *v != "(null)")
+    };
+    let command = field("Command").ok_or("the job has no batch script")?;
+    let (script, args) = split_command(command);
+    let mut form = SubmitForm::default();
+    form.set(SubmitField::Script, script.to_owned());
+    form.set(SubmitField::Args, args.to_owned());
+
+    for (field_, key) in [
+        (SubmitField::Name, "JobName"),
+        (SubmitField::Partition, "Partition"),
+        (SubmitField::Account, "Account"),
+        (SubmitField::Nodes, "NumNodes"),
+        (SubmitField::Cpus, "CPUs/Task"),
+        (SubmitField::Mem, "MinMemoryNode"),
+        (SubmitField::WorkDir, "WorkDir"),
+    ] {
+        form.set(field_, field(key).unwrap_or_default().to_owned());
+    }
+    if let Some(time) = field("TimeLimit").filter(|t| *t != "UNLIMITED") {
+        form.set(SubmitField::Time, time.to_owned());
+    }
+    // like `gres/gpu:2` or `gres/gpu:a100:2` for --gpus=2, and older versions leave out `gres/`
+    let gres = |tres: &str| {
+        tres.split(',')
+            .map(|t| {
+                t.strip_prefix("gres/")
+                    .or_else(|| t.strip_prefix("gres:"))
+                    .unwrap_or(t)
+                    .to_owned()
+            })
+            .collect::<Vec<_>>()
+    };
+    let gpus = field("TresPerJob")
+        .map(gres)
+        .unwrap_or_default()
+        .into_iter()
+        .find_map(|t| t.strip_prefix("gpu:").map(str::to_owned));
+    form.set(SubmitField::Gpus, gpus.unwrap_or_default());
+
+    let mut extra = Vec::new();
+    let ids = match (field("ArrayJobId"), field("ArrayTaskId")) {
+        (Some(array_id), Some(task_id)) => {
+            extra.push(format!("--array={}", task_id));
+            vec![
+                (format!("{}_{}", array_id, task_id), "%A_%a"),
+                (array_id.to_owned(), "%A"),
+            ]
+        }
+        _ => field("JobId")
+            .map(|id| vec![(id.to_owned(), "%j")])
+            .unwrap_or_default(),
+    };
+    for (key, option) in [
+        ("NumTasks", "--ntasks"),
+        ("MinMemoryCPU", "--mem-per-cpu"),
+        ("QOS", "--qos"),
+        ("Features", "--constraint"),
+    ] {
+        if let Some(value) = field(key) {
+            extra.push(format!("{}={}", option, shell_quote(value)));
+        }
+    }
+    if let Some(tres) = field("TresPerNode") {
+        extra.push(format!("--gres={}", shell_quote(&gres(tres).join(","))));
+    }
+    form.set(SubmitField::Extra, extra.join(" "));
+
+    let pattern = |path: &str| {
+        ids.iter().fold(path.to_owned(), |p, (id, symbol)| {
+            replace_number(&p, id, symbol)
+        })
+    };
+    let stdout = field("StdOut").map(pattern).unwrap_or_default();
+    let stderr = field("StdErr").map(pattern).unwrap_or_default();
+    // stderr goes to stdout's file unless it's given
+    if stderr != stdout {
+        form.set(SubmitField::Error, stderr);
+    }
+    form.set(SubmitField::Output, stdout);
+    Ok(form)
+}
+
+/// Splits scontrol's `Command`, the script followed by its arguments, into the two. The script is
+/// the longest part that is a file, so its path can have spaces, or else the first word.
+fn split_command(command: &str) -> (&str, &str) {
+    let script = command
+        .match_indices(' ')
+        .map(|(i, _)| &command[..i])
+        .chain([command])
+        .rfind(|script| Path::new(script).is_file())
+        .unwrap_or_else(|| command.split(' ').next().unwrap_or_default());
+    (script, command[script.len()..].trim())
+}
+
+/// Replaces `number` in `s` where it isn't part of a longer number.
+fn replace_number(s: &str, number: &str, with: &str) -> String {
+    let mut result = String::new();
+    let mut rest = s;
+    while let Some(i) = rest.find(number) {
+        let after = &rest[i + number.len()..];
+        result.push_str(&rest[..i]);
+        let is_digit = |c: Option<char>| c.is_some_and(|c| c.is_ascii_digit());
+        let whole = !is_digit(result.chars().next_back()) && !is_digit(after.chars().next());
+        result.push_str(if whole { with } else { number });
+        rest = after;
+    }
+    result + rest
+}
+
+/// Runs `command_line` and returns the id of the submitted job, or sbatch's error.
+pub fn submit(command_line: &str) -> Result<String, String> {
+    let output = Command::new("sh")
+        .arg("-c")
+        .arg(command_line)
+        .output()
+        .map_err(|e| format!("failed to run sbatch: {}", e))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(match stderr.trim() {
+            "" => format!("sbatch failed: {}", output.status),
+            e => e.lines().last().unwrap_or(e).to_owned(),
+        });
+    }
+    parse_job_id(&String::from_utf8_lossy(&output.stdout))
+        .ok_or_else(|| "sbatch printed no job id".to_string())
+}
+
+/// The job id printed by `sbatch --parsable`, which is followed by `;cluster` on multi-cluster
+/// setups.
+fn parse_job_id(output: &str) -> Option<String> {
+    let id = output.lines().last()?.split(';').next()?.trim();
+    Some(id.to_owned()).filter(|id| !id.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_command_line() {
+        let mut form = SubmitForm::default();
+        assert!(form.command_line().is_err());
+        form.set(SubmitField::Script, "train.sh".to_string());
+        form.set(SubmitField::Name, "my run".to_string());
+        form.set(SubmitField::Time, " 2:00:00 ".to_string());
+        form.set(SubmitField::Extra, "--export=ALL,X='a b'".to_string());
+        assert_eq!(
+            form.command_line().unwrap(),
+            "sbatch --parsable --job-name='my run' --time='2:00:00' --export=ALL,X='a b' 'train.sh'"
+        );
+    }
+
+    #[test]
+    fn test_resubmit_form() {
+        let scontrol = "JobId=1234 JobName=train UserId=alice(1000) Partition=gpu Account=proj QOS=normal \
+            TimeLimit=1-00:00:00 NumNodes=1-1 NumTasks=2 CPUs/Task=4 MinMemoryNode=16G \
+            TRES=cpu=4,mem=16G,node=1,billing=4,gres/gpu=2 Command=/home/alice/train.sh \
+            WorkDir=/home/alice StdErr=/logs/train-1234.out StdIn=/dev/null StdOut=/logs/train-1234.out \
+            TresPerJob=gres/gpu:2";
+        let form = resubmit_form(scontrol).unwrap();
+        assert_eq!(
+            form.command_line().unwrap(),
+            "sbatch --parsable --job-name='train' --partition='gpu' --account='proj' --time='1-00:00:00' \
+             --nodes='1-1' --cpus-per-task='4' --mem='16G' --gpus='2' --chdir='/home/alice' \
+             --output='/logs/train-%j.out' --ntasks='2' --qos='normal' '/home/alice/train.sh'"
+        );
+
+        let scontrol =
+            "JobId=1236 ArrayJobId=1234 ArrayTaskId=2 JobName=sweep TimeLimit=UNLIMITED \
+            MinMemoryCPU=2G Command=/s.sh StdOut=/logs/s-1234_2.out StdErr=/logs/s-1234.err";
+        let form = resubmit_form(scontrol).unwrap();
+        assert_eq!(form.value(SubmitField::Output), "/logs/s-%A_%a.out");
+        assert_eq!(form.value(SubmitField::Error), "/logs/s-%A.err");
+        assert_eq!(
+            form.value(SubmitField::Extra),
+            "--array=2 --mem-per-cpu='2G'"
+        );
+        assert_eq!(form.value(SubmitField::Time), "");
+
+        assert!(resubmit_form("JobId=1 Command=(null)").is_err());
+    }
+
+    #[test]
+    fn test_resubmit_form_spaces() {
+        let scontrol = "JobId=77 JobName=my run UserId=alice(1000) Features=a100&ib \
+            TresPerNode=gres/gpu:a100:2,gres/shard:1 Command=/home/alice/my runs/train.sh --lr=0.1 epochs=3 \
+            WorkDir=/home/alice/my runs StdErr=/home/alice/my runs/out 77.log StdIn=/dev/null \
+            StdOut=/home/alice/my runs/out 77.log Power=";
+        let form = resubmit_form(scontrol).unwrap();
+        // the script doesn't exist here, so its path ends at the first space
+        assert_eq!(form.value(SubmitField::Script), "/home/alice/my");
+        assert_eq!(
+            form.value(SubmitField::Args),
+            "runs/train.sh --lr=0.1 epochs=3"
+        );
+        assert_eq!(form.value(SubmitField::Name), "my run");
+        assert_eq!(form.value(SubmitField::WorkDir), "/home/alice/my runs");
+        assert_eq!(
+            form.value(SubmitField::Output),
+            "/home/alice/my runs/out %j.log"
+        );
+        assert_eq!(form.value(SubmitField::Error), "");
+        assert_eq!(form.value(SubmitField::Gpus), "");
+        assert_eq!(
+            form.value(SubmitField::Extra),
+            "--constraint='a100&ib' --gres='gpu:a100:2,shard:1'"
+        );
+    }
+
+    #[test]
+    fn test_split_command() {
+        let dir = std::env::temp_dir().join(format!("turf-split-command-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let script = dir.join("my train.sh");
+        std::fs::write(&script, "#!/bin/sh\n").unwrap();
+        let command = format!("{} --lr 0.1", script.display());
+        assert_eq!(
+            split_command(&command),
+            (script.to_str().unwrap(), "--lr 0.1")
+        );
+        assert_eq!(
+            split_command("/missing/train.sh a b"),
+            ("/missing/train.sh", "a b")
+        );
+        assert_eq!(
+            split_command("/missing/train.sh"),
+            ("/missing/train.sh", "")
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_replace_number() {
+        assert_eq!(replace_number("run12-12.out", "12", "%j"), "run%j-%j.out");
+        assert_eq!(replace_number("x123-12", "12", "%j"), "x123-%j");
+        assert_eq!(replace_number("1212", "12", "%j"), "1212");
+    }
+
+    #[test]
+    fn test_parse_job_id() {
+        assert_eq!(parse_job_id("1234\n").as_deref(), Some("1234"));
+        assert_eq!(parse_job_id("1234;cluster\n").as_deref(), Some("1234"));
+        assert_eq!(parse_job_id(""), None);
+    }
+}